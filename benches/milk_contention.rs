@@ -0,0 +1,79 @@
+//! Withdrawals from a single [`MilkBucket`] by many tasks at once, for each [`Storage`]
+//!
+//! Run with `cargo bench --bench milk_contention`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use lib::bucket::{Liters, MilkBucket, Storage};
+use shuttlings_cch24 as lib;
+
+const TASKS: usize = 64;
+const OPS_PER_TASK: usize = 2_000;
+const RUNS: usize = 7;
+
+/// every task withdraws a liter and pours it back, so the level ends where it started
+async fn contend(bucket: MilkBucket) -> usize {
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|_| {
+            let bucket = bucket.clone();
+            tokio::spawn(async move {
+                let mut empty = 0;
+                for _ in 0..OPS_PER_TASK {
+                    match bucket.try_withdraw(Liters(1.0)).await {
+                        Ok(pack) => bucket.fill_by(black_box(pack).inner()).await,
+                        Err(_) => empty += 1,
+                    }
+                }
+                empty
+            })
+        })
+        .collect();
+    let mut empty = 0;
+    for task in tasks {
+        empty += task.await.expect("task panicked");
+    }
+    empty
+}
+
+fn run(runtime: &tokio::runtime::Runtime, storage: Storage) -> Duration {
+    let mut elapsed: Vec<_> = (0..RUNS)
+        .map(|_| {
+            let bucket = MilkBucket::builder()
+                .full(TASKS as f32)
+                .initial(TASKS as f32)
+                .storage(storage)
+                .build();
+            let started = Instant::now();
+            let empty = runtime.block_on(contend(bucket.clone()));
+            let elapsed = started.elapsed();
+            let available = runtime.block_on(bucket.available());
+            assert_eq!(empty, 0, "{storage:?}: a withdrawal failed");
+            assert_eq!(
+                available,
+                Liters(TASKS as f32),
+                "{storage:?}: milk was lost"
+            );
+            elapsed
+        })
+        .collect();
+    elapsed.sort();
+    elapsed[RUNS / 2]
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build runtime");
+    let ops = (TASKS * OPS_PER_TASK * 2) as f64;
+    println!("{TASKS} tasks, {OPS_PER_TASK} withdrawals and fills each, median of {RUNS} runs");
+    for storage in [Storage::Mutex, Storage::Atomic] {
+        let elapsed = run(&runtime, storage);
+        println!(
+            "{:>6}: {elapsed:>10.2?} ({:.0} ops/s)",
+            format!("{storage:?}"),
+            ops / elapsed.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS "submissions" (
+    "id" UUID PRIMARY KEY,
+    "package_name" TEXT NOT NULL,
+    "package_version" TEXT,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS "submission_orders" (
+    "submission_id" UUID NOT NULL REFERENCES "submissions" ("id") ON DELETE CASCADE,
+    "position" INT NOT NULL,
+    "item" TEXT NOT NULL,
+    "quantity" BIGINT NOT NULL,
+    PRIMARY KEY ("submission_id", "position")
+);
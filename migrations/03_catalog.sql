@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS "catalog_items" (
+    "name" TEXT PRIMARY KEY,
+    "stock" BIGINT NOT NULL CHECK ("stock" >= 0),
+    "unit_price" BIGINT NOT NULL CHECK ("unit_price" >= 0)
+);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS "milk_snapshots" (
+    "name" TEXT PRIMARY KEY,
+    "level" DOUBLE PRECISION NOT NULL CHECK ("level" >= 0),
+    "saved_at" TIMESTAMPTZ NOT NULL
+);
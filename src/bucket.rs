@@ -1,11 +1,22 @@
 use std::sync::Arc;
 
+pub mod clients;
+pub mod clock;
+mod level;
+pub mod limiter;
 pub mod milk;
-mod unit;
+pub mod unit;
 
 #[derive(Debug, Clone)]
 pub struct MilkBucket {
     inner: Arc<milk::Inner>,
 }
 
+/// [`MilkBucket`]s per client
+#[derive(Debug, Clone)]
+pub struct ClientBuckets {
+    inner: Arc<clients::Inner>,
+}
+
+pub use level::Storage;
 pub use unit::{Gallons, Liters, Litres, Pints};
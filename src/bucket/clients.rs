@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use super::limiter::{Algorithm, RateLimiter};
+use super::milk::RefillRate;
+use super::Liters;
+
+// MARK: key
+
+/// How to tell clients apart
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum KeyBy {
+    /// one bucket shared by everyone
+    #[default]
+    Global,
+    RemoteAddr,
+    /// a claim of the JWT in the auth cookie, falling back to the remote address
+    Claim(String),
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("unknown bucket key {0:?}, expected one of `global`, `remote_addr` or `claim:<name>`")]
+pub struct ParseKeyByError(String);
+
+impl FromStr for KeyBy {
+    type Err = ParseKeyByError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "global" => Ok(Self::Global),
+            "remote_addr" => Ok(Self::RemoteAddr),
+            _ => match s.strip_prefix("claim:") {
+                Some(claim) if !claim.is_empty() => Ok(Self::Claim(claim.to_string())),
+                _ => Err(ParseKeyByError(s.to_string())),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ClientKey {
+    Global,
+    Addr(IpAddr),
+    Claim(String),
+}
+
+impl fmt::Display for ClientKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => f.write_str("global"),
+            Self::Addr(addr) => write!(f, "addr:{addr}"),
+            Self::Claim(claim) => write!(f, "claim:{claim}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("unknown client key {0:?}, expected one of `global`, `addr:<ip>` or `claim:<value>`")]
+pub struct ParseClientKeyError(String);
+
+/// The inverse of [`fmt::Display`]
+impl FromStr for ClientKey {
+    type Err = ParseClientKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "global" {
+            return Ok(Self::Global);
+        }
+        if let Some(addr) = s.strip_prefix("addr:").and_then(|a| a.parse().ok()) {
+            return Ok(Self::Addr(addr));
+        }
+        match s.strip_prefix("claim:") {
+            Some(claim) => Ok(Self::Claim(claim.to_string())),
+            None => Err(ParseClientKeyError(s.to_string())),
+        }
+    }
+}
+
+impl Serialize for ClientKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// MARK: Inner
+
+#[derive(Debug)]
+struct Entry {
+    limiter: Arc<dyn RateLimiter>,
+    last_seen: Instant,
+    /// refill task of [`Algorithm::Bucket`]
+    refill: Option<JoinHandle<()>>,
+    /// address which introduced a [`ClientKey::Claim`]
+    origin: Option<IpAddr>,
+}
+
+#[derive(Debug)]
+pub(super) struct Inner {
+    key_by: KeyBy,
+    algorithm: Algorithm,
+    full: Liters,
+    rate_tx: watch::Sender<RefillRate>,
+    idle_timeout: Duration,
+    max_clients: usize,
+    claims_per_addr: usize,
+    buckets: Mutex<HashMap<ClientKey, Entry>>,
+}
+
+// MARK: Builder
+
+pub struct Builder<Full = (), Rate = ()> {
+    full: Full,
+    rate: Rate,
+    key_by: KeyBy,
+    algorithm: Algorithm,
+    idle_timeout: Duration,
+    max_clients: usize,
+    claims_per_addr: usize,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            full: (),
+            rate: (),
+            key_by: KeyBy::default(),
+            algorithm: Algorithm::default(),
+            idle_timeout: Duration::from_secs(300),
+            max_clients: 10_000,
+            claims_per_addr: 4,
+        }
+    }
+}
+
+impl super::ClientBuckets {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+impl<Full, Rate> Builder<Full, Rate> {
+    pub fn full(self, value: f32) -> Builder<Liters, Rate> {
+        let Self {
+            rate,
+            key_by,
+            algorithm,
+            idle_timeout,
+            max_clients,
+            claims_per_addr,
+            ..
+        } = self;
+        Builder {
+            full: Liters(value),
+            rate,
+            key_by,
+            algorithm,
+            idle_timeout,
+            max_clients,
+            claims_per_addr,
+        }
+    }
+
+    pub fn refill_rate(self, value: RefillRate) -> Builder<Full, RefillRate> {
+        let Self {
+            full,
+            key_by,
+            algorithm,
+            idle_timeout,
+            max_clients,
+            claims_per_addr,
+            ..
+        } = self;
+        Builder {
+            full,
+            rate: value,
+            key_by,
+            algorithm,
+            idle_timeout,
+            max_clients,
+            claims_per_addr,
+        }
+    }
+
+    pub fn key_by(self, value: KeyBy) -> Self {
+        Self {
+            key_by: value,
+            ..self
+        }
+    }
+
+    /// [`Algorithm::Bucket`] by default
+    pub fn algorithm(self, value: Algorithm) -> Self {
+        Self {
+            algorithm: value,
+            ..self
+        }
+    }
+
+    /// full buckets unused for this long are evicted
+    pub fn idle_timeout(self, value: Duration) -> Self {
+        Self {
+            idle_timeout: value,
+            ..self
+        }
+    }
+
+    /// at most this many buckets are kept, 10000 by default
+    pub fn max_clients(self, value: usize) -> Self {
+        Self {
+            max_clients: value,
+            ..self
+        }
+    }
+
+    /// at most this many [`ClientKey::Claim`]s are introduced by one address, 4 by default
+    ///
+    /// Anyone can sign claims at `/16/wrap`, so further ones are keyed by the address instead.
+    pub fn claims_per_addr(self, value: usize) -> Self {
+        Self {
+            claims_per_addr: value,
+            ..self
+        }
+    }
+}
+
+impl Builder<Liters, RefillRate> {
+    pub fn build(self) -> super::ClientBuckets {
+        let Self {
+            full,
+            rate,
+            key_by,
+            algorithm,
+            idle_timeout,
+            max_clients,
+            claims_per_addr,
+        } = self;
+        let (rate_tx, _) = watch::channel(rate);
+        let inner = Inner {
+            key_by,
+            algorithm,
+            full,
+            rate_tx,
+            idle_timeout,
+            max_clients,
+            claims_per_addr,
+            buckets: Mutex::new(HashMap::new()),
+        };
+        super::ClientBuckets {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+// MARK: ops
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketStatus {
+    client: ClientKey,
+    level: Liters,
+    full: Liters,
+    idle_secs: f32,
+}
+
+impl BucketStatus {
+    pub fn client(&self) -> &ClientKey {
+        &self.client
+    }
+
+    pub fn level(&self) -> Liters {
+        self.level
+    }
+}
+
+impl super::ClientBuckets {
+    pub fn key_by(&self) -> &KeyBy {
+        &self.inner.key_by
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.inner.algorithm
+    }
+
+    /// Limiter of the client, created full on first use
+    ///
+    /// [`None`] for a new client while [`Builder::max_clients`] buckets are kept.
+    #[tracing::instrument(skip(self))]
+    pub async fn get(&self, key: ClientKey) -> Option<Arc<dyn RateLimiter>> {
+        let mut buckets = self.inner.buckets.lock().await;
+        if let Some(entry) = buckets.get_mut(&key) {
+            entry.last_seen = Instant::now();
+            return Some(Arc::clone(&entry.limiter));
+        }
+        let full = self.inner.full;
+        self.insert(&mut buckets, key, full, None)
+    }
+
+    /// Create the bucket of the client with `level` available, unless it exists already
+    #[tracing::instrument(skip(self))]
+    pub async fn restore(&self, key: ClientKey, level: Liters) {
+        let mut buckets = self.inner.buckets.lock().await;
+        if !buckets.contains_key(&key) {
+            self.insert(&mut buckets, key, level, None);
+        }
+    }
+
+    /// [`ClientKey::Claim`] of `claim`, or [`ClientKey::Addr`] once `addr` has introduced
+    /// [`Builder::claims_per_addr`] other claims
+    #[tracing::instrument(skip(self))]
+    pub async fn claim_key(&self, claim: String, addr: Option<IpAddr>) -> ClientKey {
+        let key = ClientKey::Claim(claim);
+        let mut buckets = self.inner.buckets.lock().await;
+        let Some(addr) = addr else {
+            return key;
+        };
+        if buckets.contains_key(&key) {
+            return key;
+        }
+        let introduced = buckets.values().filter(|e| e.origin == Some(addr)).count();
+        if introduced >= self.inner.claims_per_addr {
+            tracing::info!("too many claims from one address, keying by the address");
+            return ClientKey::Addr(addr);
+        }
+        self.insert(&mut buckets, key.clone(), self.inner.full, Some(addr));
+        key
+    }
+
+    fn insert(
+        &self,
+        buckets: &mut HashMap<ClientKey, Entry>,
+        key: ClientKey,
+        initial: Liters,
+        origin: Option<IpAddr>,
+    ) -> Option<Arc<dyn RateLimiter>> {
+        if buckets.len() >= self.inner.max_clients {
+            tracing::warn!(max = self.inner.max_clients, "too many client buckets");
+            return None;
+        }
+        let full = self.inner.full;
+        let limiter = self
+            .inner
+            .algorithm
+            .build(full, initial, self.refill_rate());
+        let refill = limiter
+            .as_bucket()
+            .map(|bucket| tokio::spawn(bucket.clone().refill_task()));
+        tracing::info!("created a bucket");
+        let entry = Entry {
+            limiter: Arc::clone(&limiter),
+            last_seen: Instant::now(),
+            refill,
+            origin,
+        };
+        buckets.insert(key, entry);
+        Some(limiter)
+    }
+
+    pub fn refill_rate(&self) -> RefillRate {
+        *self.inner.rate_tx.borrow()
+    }
+
+    /// Applies to existing buckets as well as ones created later
+    pub async fn set_refill_rate(&self, rate: RefillRate) {
+        let buckets = self.inner.buckets.lock().await;
+        self.inner.rate_tx.send_replace(rate);
+        for entry in buckets.values() {
+            entry.limiter.set_refill_rate(rate);
+        }
+    }
+
+    pub async fn statuses(&self) -> Vec<BucketStatus> {
+        let buckets = self.inner.buckets.lock().await;
+        let mut statuses = Vec::with_capacity(buckets.len());
+        for (key, entry) in buckets.iter() {
+            statuses.push(BucketStatus {
+                client: key.clone(),
+                level: entry.limiter.quota().await.remaining(),
+                full: self.inner.full,
+                idle_secs: entry.last_seen.elapsed().as_secs_f32(),
+            });
+        }
+        statuses.sort_by(|l, r| l.client.cmp(&r.client));
+        statuses
+    }
+
+    /// Drop buckets which are full, idle and not streamed, returning how many were dropped
+    pub async fn evict_idle(&self) -> usize {
+        let mut buckets = self.inner.buckets.lock().await;
+        let mut idle = vec![];
+        for (key, entry) in buckets.iter() {
+            if entry.last_seen.elapsed() >= self.inner.idle_timeout && entry.limiter.is_idle().await
+            {
+                idle.push(key.clone());
+            }
+        }
+        for key in &idle {
+            if let Some(refill) = buckets.remove(key).and_then(|e| e.refill) {
+                refill.abort();
+            }
+        }
+        idle.len()
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn evict_task(self) {
+        let mut interval = tokio::time::interval(self.inner.idle_timeout);
+        interval.tick().await; // ignore immediate tick
+        loop {
+            interval.tick().await;
+            let evicted = self.evict_idle().await;
+            if evicted > 0 {
+                tracing::info!(evicted, "evicted idle buckets");
+            }
+        }
+    }
+}
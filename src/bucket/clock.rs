@@ -0,0 +1,164 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+/// Source of time for [`MilkBucket`](super::MilkBucket)
+pub trait Clock: fmt::Debug + Send + Sync + 'static {
+    fn now(&self) -> Instant;
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()>;
+}
+
+/// [`tokio::time`], which is also paused and advanced by `tokio::time::pause`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep_until(deadline))
+    }
+}
+
+// MARK: ManualClock
+
+/// Virtual time which only moves by [`ManualClock::advance`]
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    inner: Arc<Mutex<Manual>>,
+}
+
+#[derive(Debug)]
+struct Manual {
+    now: Instant,
+    sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        let manual = Manual {
+            now: Instant::now(),
+            sleepers: vec![],
+        };
+        Self {
+            inner: Arc::new(Mutex::new(manual)),
+        }
+    }
+
+    /// number of sleeps not woken yet
+    pub fn sleepers(&self) -> usize {
+        let manual = self.inner.lock().unwrap();
+        manual
+            .sleepers
+            .iter()
+            .filter(|(_, tx)| !tx.is_closed())
+            .count()
+    }
+
+    /// Move time forward, waking sleepers one deadline at a time and letting woken tasks run
+    ///
+    /// Tasks are expected to run on the current thread, as in `#[tokio::test]`.
+    pub async fn advance(&self, by: Duration) {
+        settle().await;
+        let target = self.now() + by;
+        loop {
+            let next = {
+                let manual = self.inner.lock().unwrap();
+                manual
+                    .sleepers
+                    .iter()
+                    .map(|(deadline, _)| *deadline)
+                    .filter(|deadline| *deadline <= target)
+                    .min()
+            };
+            let Some(next) = next else {
+                break;
+            };
+            self.wake_until(next);
+            settle().await;
+        }
+        self.wake_until(target);
+        settle().await;
+    }
+
+    fn wake_until(&self, now: Instant) {
+        let mut manual = self.inner.lock().unwrap();
+        manual.now = manual.now.max(now);
+        let (woken, sleeping) = std::mem::take(&mut manual.sleepers)
+            .into_iter()
+            .partition(|(deadline, _)| *deadline <= now);
+        manual.sleepers = sleeping;
+        drop(manual);
+        for (_, tx) in woken {
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// let woken tasks run until they wait again
+async fn settle() {
+    for _ in 0..16 {
+        tokio::task::yield_now().await;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.inner.lock().unwrap().now
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        let mut manual = self.inner.lock().unwrap();
+        if deadline <= manual.now {
+            return Box::pin(std::future::ready(()));
+        }
+        let (tx, rx) = oneshot::channel();
+        manual.sleepers.push((deadline, tx));
+        Box::pin(async move {
+            // the clock is gone, nothing will wake us
+            if rx.await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        })
+    }
+}
+
+// MARK: Interval
+
+/// [`tokio::time::Interval`] on a [`Clock`], catching up missed ticks in a burst
+#[derive(Debug)]
+pub(super) struct Interval {
+    clock: Arc<dyn Clock>,
+    next: Instant,
+    period: Duration,
+}
+
+impl Interval {
+    /// first tick is after `period`, unlike [`tokio::time::interval`]
+    pub(super) fn new(clock: Arc<dyn Clock>, period: Duration) -> Self {
+        let next = clock.now() + period;
+        Self {
+            clock,
+            next,
+            period,
+        }
+    }
+
+    pub(super) async fn tick(&mut self) {
+        self.clock.sleep_until(self.next).await;
+        self.next += self.period;
+    }
+}
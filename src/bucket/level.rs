@@ -0,0 +1,188 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::Liters;
+
+/// How [`MilkBucket`](super::MilkBucket) keeps its level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Storage {
+    /// [`Liters`] behind a [`tokio::sync::Mutex`], every operation takes the lock
+    Mutex,
+    /// millilitres in an [`AtomicU64`], updated by compare-and-swap loops
+    ///
+    /// Levels are kept in whole millilitres, up to [`u32::MAX`] of them (about 4.3 million
+    /// liters), so the initial level and refills are rounded to the nearest millilitre.
+    /// Withdrawals are rounded the same for both storages.
+    #[default]
+    Atomic,
+}
+
+/// Level with the number of changes so far, wrapping
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Counted {
+    liters: Liters,
+    seq: u32,
+}
+
+/// Both storages order every [`Level::update`] and [`Level::order`] in a single sequence,
+/// so that they behave the same under contention
+#[derive(Debug)]
+pub(super) enum Level {
+    Mutex(Mutex<Counted>),
+    /// millilitres in the lower 32 bits, [`Counted::seq`] in the upper 32
+    Atomic(AtomicU64),
+}
+
+/// A change of the level by [`Level::update`]
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Change {
+    pub(super) before: Liters,
+    pub(super) after: Liters,
+    seq: u32,
+}
+
+impl Change {
+    /// whether this change came after the one of `seq`
+    ///
+    /// Right as long as fewer than [`i32::MAX`] changes happen in between.
+    pub(super) fn follows(&self, seq: u32) -> bool {
+        (self.seq.wrapping_sub(seq) as i32) > 0
+    }
+
+    pub(super) fn seq(&self) -> u32 {
+        self.seq
+    }
+}
+
+const MILLILITRES_PER_LITER: f64 = 1000.0;
+const SEQ_SHIFT: u32 = 32;
+const MILLILITRES_MASK: u64 = (1 << SEQ_SHIFT) - 1;
+
+fn to_millilitres(liters: Liters) -> u64 {
+    let millilitres = (liters.0 as f64 * MILLILITRES_PER_LITER).round().max(0.0) as u64;
+    millilitres.min(MILLILITRES_MASK)
+}
+
+/// `liters` in the whole millilitres [`Storage::Atomic`] keeps, at least one if positive
+///
+/// Withdrawals are rounded so, as anything smaller would be rounded away from the level.
+pub(super) fn whole_millilitres(liters: Liters) -> Liters {
+    if liters.0 <= 0.0 {
+        return liters;
+    }
+    to_liters(to_millilitres(liters).max(1))
+}
+
+fn to_liters(packed: u64) -> Liters {
+    Liters(((packed & MILLILITRES_MASK) as f64 / MILLILITRES_PER_LITER) as f32)
+}
+
+fn seq_of(packed: u64) -> u32 {
+    (packed >> SEQ_SHIFT) as u32
+}
+
+fn pack(liters: Liters, seq: u32) -> u64 {
+    (u64::from(seq) << SEQ_SHIFT) | to_millilitres(liters)
+}
+
+impl Level {
+    pub(super) fn new(storage: Storage, initial: Liters) -> Self {
+        match storage {
+            Storage::Mutex => Self::Mutex(Mutex::new(Counted {
+                liters: initial,
+                seq: 0,
+            })),
+            Storage::Atomic => Self::Atomic(AtomicU64::new(pack(initial, 0))),
+        }
+    }
+
+    pub(super) fn storage(&self) -> Storage {
+        match self {
+            Self::Mutex(_) => Storage::Mutex,
+            Self::Atomic(_) => Storage::Atomic,
+        }
+    }
+
+    pub(super) async fn load(&self) -> Liters {
+        match self {
+            Self::Mutex(level) => level.lock().await.liters,
+            Self::Atomic(level) => to_liters(level.load(Ordering::Acquire)),
+        }
+    }
+
+    /// Run `f` in the sequence of [`Self::update`]s
+    ///
+    /// An update which read the level before `f` fails and retries for [`Storage::Atomic`],
+    /// as it waits for the lock for [`Storage::Mutex`]. So whatever `f` changes is seen by
+    /// every update after it, such as someone joining a queue.
+    pub(super) async fn order<R>(&self, f: impl FnOnce() -> R) -> R {
+        match self {
+            Self::Mutex(level) => {
+                let mut level = level.lock().await;
+                level.seq = level.seq.wrapping_add(1);
+                f()
+            }
+            Self::Atomic(level) => {
+                let r = f();
+                level.fetch_add(1 << SEQ_SHIFT, Ordering::AcqRel);
+                r
+            }
+        }
+    }
+
+    /// Replace the level with `f(before)` unless it returns `None`
+    ///
+    /// `on_change` is called right after the update, while still holding the lock
+    /// for [`Storage::Mutex`]. Returns the level `f` refused otherwise.
+    pub(super) async fn update<F, C>(&self, f: F, on_change: C) -> Result<Change, Liters>
+    where
+        F: Fn(Liters) -> Option<Liters>,
+        C: FnOnce(Change),
+    {
+        match self {
+            Self::Mutex(level) => {
+                let mut level = level.lock().await;
+                let before = level.liters;
+                let after = f(before).ok_or(before)?;
+                level.seq = level.seq.wrapping_add(1);
+                level.liters = after;
+                let change = Change {
+                    before,
+                    after,
+                    seq: level.seq,
+                };
+                on_change(change);
+                Ok(change)
+            }
+            Self::Atomic(level) => {
+                let mut current = level.load(Ordering::Acquire);
+                loop {
+                    let before = to_liters(current);
+                    let after = f(before).ok_or(before)?;
+                    let seq = seq_of(current).wrapping_add(1);
+                    let new = pack(after, seq);
+                    match level.compare_exchange_weak(
+                        current,
+                        new,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    ) {
+                        Ok(_) => {
+                            let change = Change {
+                                before,
+                                after: to_liters(new),
+                                seq,
+                            };
+                            on_change(change);
+                            return Ok(change);
+                        }
+                        Err(actual) => current = actual,
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use super::milk::{Empty, Pack, Quota, RefillRate};
+use super::{Liters, MilkBucket};
+
+mod fixed_window;
+mod gcra;
+mod lazy;
+mod sliding_log;
+
+pub use fixed_window::FixedWindow;
+pub use gcra::Gcra;
+pub use lazy::LazyBucket;
+pub use sliding_log::SlidingLog;
+
+// MARK: trait
+
+/// Decides whether milk may be withdrawn
+///
+/// Every algorithm allows bursts up to the capacity, and refills by the [`RefillRate`] on average.
+pub trait RateLimiter: fmt::Debug + Send + Sync + 'static {
+    fn algorithm(&self) -> Algorithm;
+
+    /// Withdraw all of `liters` or nothing
+    fn try_acquire(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>>;
+
+    /// [`Self::try_acquire`], waiting up to `wait` in a FIFO queue
+    ///
+    /// [`Self::try_acquire`] fails while someone is waiting, so nobody jumps the queue.
+    /// Dropping the future leaves the queue without withdrawing anything.
+    fn acquire_waiting(&self, liters: Liters, wait: Duration)
+        -> BoxFuture<'_, Result<Pack, Empty>>;
+
+    /// Withdraw `liters`, or all that is available if less, failing only when nothing is
+    fn try_acquire_up_to(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(async move {
+            let mut request = liters;
+            loop {
+                let empty = match self.try_acquire(request).await {
+                    Ok(pack) => return Ok(pack),
+                    Err(e) => e,
+                };
+                // someone else may withdraw in between, then try again with less
+                let available = empty.available();
+                if available.0 <= 0.0 || available.0 >= request.0 {
+                    return Err(empty);
+                }
+                request = available;
+            }
+        })
+    }
+
+    fn quota(&self) -> BoxFuture<'_, Quota>;
+
+    /// Make the whole capacity available again
+    fn fulfill(&self) -> BoxFuture<'_, ()>;
+
+    fn refill_rate(&self) -> RefillRate;
+
+    fn set_refill_rate(&self, rate: RefillRate);
+
+    /// Whether it is back to the initial full state, so that dropping it loses nothing
+    fn is_idle(&self) -> BoxFuture<'_, bool>;
+
+    /// The [`MilkBucket`] behind [`Algorithm::Bucket`], which needs its refill task
+    fn as_bucket(&self) -> Option<&MilkBucket> {
+        None
+    }
+}
+
+impl RateLimiter for MilkBucket {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Bucket
+    }
+
+    fn try_acquire(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(self.try_withdraw(liters))
+    }
+
+    /// queue of its own, served on refill ticks
+    fn acquire_waiting(
+        &self,
+        liters: Liters,
+        wait: Duration,
+    ) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(self.withdraw_waiting(liters, wait))
+    }
+
+    fn try_acquire_up_to(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(self.try_withdraw_up_to(liters))
+    }
+
+    fn quota(&self) -> BoxFuture<'_, Quota> {
+        Box::pin(MilkBucket::quota(self))
+    }
+
+    fn fulfill(&self) -> BoxFuture<'_, ()> {
+        Box::pin(MilkBucket::fulfill(self))
+    }
+
+    fn refill_rate(&self) -> RefillRate {
+        MilkBucket::refill_rate(self)
+    }
+
+    fn set_refill_rate(&self, rate: RefillRate) {
+        MilkBucket::set_refill_rate(self, rate);
+    }
+
+    fn is_idle(&self) -> BoxFuture<'_, bool> {
+        Box::pin(async move { !self.is_watched() && self.is_full().await })
+    }
+
+    fn as_bucket(&self) -> Option<&MilkBucket> {
+        Some(self)
+    }
+}
+
+// MARK: Algorithm
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    /// [`MilkBucket`], refilled by a background task
+    #[default]
+    Bucket,
+    /// [`LazyBucket`]
+    Lazy,
+    /// [`Gcra`]
+    Gcra,
+    /// [`SlidingLog`]
+    SlidingLog,
+    /// [`FixedWindow`]
+    FixedWindow,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(
+    "unknown rate limiting algorithm {0:?}, expected one of `bucket`, `lazy`, `gcra`, `sliding_log` or `fixed_window`"
+)]
+pub struct ParseAlgorithmError(String);
+
+impl FromStr for Algorithm {
+    type Err = ParseAlgorithmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bucket" => Ok(Self::Bucket),
+            "lazy" => Ok(Self::Lazy),
+            "gcra" => Ok(Self::Gcra),
+            "sliding_log" => Ok(Self::SlidingLog),
+            "fixed_window" => Ok(Self::FixedWindow),
+            _ => Err(ParseAlgorithmError(s.to_string())),
+        }
+    }
+}
+
+impl Algorithm {
+    /// A limiter of `full` liters with `initial` available
+    ///
+    /// The refill task of [`Algorithm::Bucket`] is not started here.
+    pub fn build(self, full: Liters, initial: Liters, rate: RefillRate) -> Arc<dyn RateLimiter> {
+        match self {
+            Self::Bucket => {
+                let bucket = MilkBucket::builder()
+                    .full(full.0)
+                    .initial(initial.0)
+                    .refill_rate(rate)
+                    .build();
+                Arc::new(bucket)
+            }
+            Self::Lazy => Arc::new(LazyBucket::new(full, initial, rate)),
+            Self::Gcra => Arc::new(Gcra::new(full, initial, rate)),
+            Self::SlidingLog => Arc::new(SlidingLog::new(full, initial, rate)),
+            Self::FixedWindow => Arc::new(FixedWindow::new(full, initial, rate)),
+        }
+    }
+}
+
+// MARK: WaitQueue
+
+#[derive(Debug, Default)]
+struct Tickets {
+    next: u64,
+    queue: VecDeque<u64>,
+}
+
+/// FIFO queue of [`RateLimiter::acquire_waiting`] for the algorithms refilled on access
+#[derive(Debug)]
+struct WaitQueue {
+    tx: watch::Sender<Tickets>,
+}
+
+/// Place in a [`WaitQueue`], left on drop
+struct Ticket<'a> {
+    queue: &'a WaitQueue,
+    number: u64,
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self {
+            tx: watch::Sender::new(Tickets::default()),
+        }
+    }
+}
+
+impl WaitQueue {
+    fn is_empty(&self) -> bool {
+        self.tx.borrow().queue.is_empty()
+    }
+
+    /// `acquire(true)` for the first in the queue, `acquire(false)` to report without withdrawing
+    /// for the others
+    async fn acquire_waiting<F>(&self, wait: Duration, acquire: F) -> Result<Pack, Empty>
+    where
+        F: Fn(bool) -> Result<Pack, Empty>,
+    {
+        let deadline = Instant::now() + wait;
+        let mut rx = self.tx.subscribe();
+        let ticket = Ticket::take(self);
+        loop {
+            rx.mark_unchanged();
+            let first = ticket.is_first();
+            let empty = match acquire(first) {
+                Ok(pack) => return Ok(pack),
+                Err(e) => e,
+            };
+            let Some(retry_after) = empty.retry_after() else {
+                // never refilled enough
+                return Err(empty);
+            };
+            // the others are woken when the queue moves
+            let wake_at = if first {
+                deadline.min(Instant::now() + retry_after)
+            } else {
+                deadline
+            };
+            tokio::select! {
+                _ = tokio::time::sleep_until(wake_at) => {
+                    if Instant::now() >= deadline {
+                        tracing::info!("gave up waiting for milk");
+                        return Err(empty);
+                    }
+                }
+                _ = rx.changed() => {}
+            }
+        }
+    }
+}
+
+impl<'a> Ticket<'a> {
+    fn take(queue: &'a WaitQueue) -> Self {
+        let mut number = 0;
+        queue.tx.send_modify(|t| {
+            number = t.next;
+            t.next += 1;
+            t.queue.push_back(number);
+        });
+        Self { queue, number }
+    }
+
+    fn is_first(&self) -> bool {
+        self.queue.tx.borrow().queue.front() == Some(&self.number)
+    }
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let number = self.number;
+        self.queue
+            .tx
+            .send_modify(|t| t.queue.retain(|n| *n != number));
+    }
+}
+
+// MARK: helpers
+
+/// a refill stopped by a zero amount is taken as one liter per this many seconds, about 317 years
+const NEVER_SECS: f64 = 1e10;
+
+/// seconds to refill a liter
+fn secs_per_liter(rate: &RefillRate) -> f64 {
+    let amount = rate.amount().0 as f64;
+    if amount <= 0.0 {
+        return NEVER_SECS;
+    }
+    f64::min(rate.duration().as_secs_f64() / amount, NEVER_SECS)
+}
+
+/// `None` for [`NEVER_SECS`] or longer
+fn duration_of(secs: f64) -> Option<Duration> {
+    if secs >= NEVER_SECS {
+        return None;
+    }
+    Some(Duration::from_secs_f64(secs.max(0.0)))
+}
+
+/// seconds since `origin`
+fn secs_since(origin: Instant) -> f64 {
+    origin.elapsed().as_secs_f64()
+}
+
+/// until `level` refills to the next whole liter
+fn next_liter(level: f64, secs_per_liter: f64) -> Duration {
+    duration_of((level.floor() + 1.0 - level) * secs_per_liter).unwrap_or(Duration::MAX)
+}
+
+fn liters(value: f64) -> Liters {
+    Liters(value as f32)
+}
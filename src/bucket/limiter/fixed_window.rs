@@ -0,0 +1,146 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio::time::Instant;
+
+use super::{duration_of, liters, secs_per_liter, secs_since, Algorithm, RateLimiter, WaitQueue};
+use crate::bucket::milk::{Empty, Pack, Quota, RefillRate};
+use crate::bucket::Liters;
+
+/// Counter of liters withdrawn in the current window, reset when the window ends
+///
+/// Windows are the time to refill `full` liters at the rate, aligned to the creation.
+#[derive(Debug)]
+pub struct FixedWindow {
+    full: f64,
+    origin: Instant,
+    state: Mutex<State>,
+    queue: WaitQueue,
+}
+
+#[derive(Debug)]
+struct State {
+    /// start of the current window in seconds since `origin`
+    start: f64,
+    used: f64,
+    rate: RefillRate,
+}
+
+impl State {
+    fn window(&self, full: f64) -> f64 {
+        full * secs_per_liter(&self.rate)
+    }
+
+    fn roll(&mut self, full: f64, now: f64) {
+        let window = self.window(full);
+        if now < self.start + window {
+            return;
+        }
+        self.start += ((now - self.start) / window).floor() * window;
+        self.used = 0.0;
+    }
+
+    fn until_next_window(&self, full: f64, now: f64) -> f64 {
+        self.start + self.window(full) - now
+    }
+
+    fn quota(&self, full: f64, now: f64) -> Quota {
+        let reset = if self.used > 0.0 {
+            duration_of(self.until_next_window(full, now))
+        } else {
+            Some(std::time::Duration::ZERO)
+        };
+        Quota::new(liters(full), liters(full - self.used), reset)
+    }
+}
+
+impl FixedWindow {
+    /// `full` has to be positive, or the window is empty
+    pub fn new(full: Liters, initial: Liters, rate: RefillRate) -> Self {
+        let full = full.0 as f64;
+        let state = State {
+            start: 0.0,
+            used: (full - initial.0 as f64).max(0.0),
+            rate,
+        };
+        Self {
+            full,
+            origin: Instant::now(),
+            state: Mutex::new(state),
+            queue: WaitQueue::default(),
+        }
+    }
+
+    /// Nothing is withdrawn unless `withdraw`, for someone waiting ahead
+    fn acquire(&self, request: Liters, withdraw: bool) -> Result<Pack, Empty> {
+        let mut state = self.state.lock().unwrap();
+        let now = secs_since(self.origin);
+        state.roll(self.full, now);
+        let request = request.0 as f64;
+        let next_window = state.until_next_window(self.full, now);
+        if !withdraw || state.used + request > self.full {
+            let retry_after = if request > self.full {
+                None
+            } else {
+                duration_of(next_window)
+            };
+            return Err(Empty::new(state.quota(self.full, now), retry_after));
+        }
+        state.used += request;
+        Ok(Pack::new(
+            liters(request),
+            state.quota(self.full, now),
+            duration_of(next_window).unwrap_or(std::time::Duration::MAX),
+        ))
+    }
+}
+
+impl RateLimiter for FixedWindow {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::FixedWindow
+    }
+
+    fn try_acquire(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>> {
+        let withdraw = self.queue.is_empty();
+        Box::pin(std::future::ready(self.acquire(liters, withdraw)))
+    }
+
+    fn acquire_waiting(
+        &self,
+        liters: Liters,
+        wait: Duration,
+    ) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(
+            self.queue
+                .acquire_waiting(wait, move |first| self.acquire(liters, first)),
+        )
+    }
+
+    fn quota(&self) -> BoxFuture<'_, Quota> {
+        let mut state = self.state.lock().unwrap();
+        let now = secs_since(self.origin);
+        state.roll(self.full, now);
+        Box::pin(std::future::ready(state.quota(self.full, now)))
+    }
+
+    fn fulfill(&self) -> BoxFuture<'_, ()> {
+        self.state.lock().unwrap().used = 0.0;
+        Box::pin(std::future::ready(()))
+    }
+
+    fn refill_rate(&self) -> RefillRate {
+        self.state.lock().unwrap().rate
+    }
+
+    /// changes the length of the current window and the following ones
+    fn set_refill_rate(&self, rate: RefillRate) {
+        self.state.lock().unwrap().rate = rate;
+    }
+
+    fn is_idle(&self) -> BoxFuture<'_, bool> {
+        let mut state = self.state.lock().unwrap();
+        state.roll(self.full, secs_since(self.origin));
+        Box::pin(std::future::ready(state.used <= 0.0))
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio::time::Instant;
+
+use super::{
+    duration_of, liters, next_liter, secs_per_liter, secs_since, Algorithm, RateLimiter, WaitQueue,
+};
+use crate::bucket::milk::{Empty, Pack, Quota, RefillRate};
+use crate::bucket::Liters;
+
+/// Generic cell rate algorithm, keeping only the theoretical arrival time
+///
+/// A liter is emitted every `T` seconds, and bursts of `full` liters are tolerated.
+#[derive(Debug)]
+pub struct Gcra {
+    full: f64,
+    origin: Instant,
+    state: Mutex<State>,
+    queue: WaitQueue,
+}
+
+#[derive(Debug)]
+struct State {
+    /// theoretical arrival time in seconds since `origin`
+    tat: f64,
+    rate: RefillRate,
+}
+
+impl Gcra {
+    pub fn new(full: Liters, initial: Liters, rate: RefillRate) -> Self {
+        let full = full.0 as f64;
+        // as if `full - initial` liters were just withdrawn
+        let tat = (full - initial.0 as f64).max(0.0) * secs_per_liter(&rate);
+        Self {
+            full,
+            origin: Instant::now(),
+            state: Mutex::new(State { tat, rate }),
+            queue: WaitQueue::default(),
+        }
+    }
+
+    fn quota_at(&self, state: &State, now: f64) -> Quota {
+        let spl = secs_per_liter(&state.rate);
+        let ahead = (state.tat - now).max(0.0);
+        let remaining = (self.full - ahead / spl).max(0.0);
+        Quota::new(liters(self.full), liters(remaining), duration_of(ahead))
+    }
+
+    /// Nothing is withdrawn unless `withdraw`, for someone waiting ahead
+    fn acquire(&self, request: Liters, withdraw: bool) -> Result<Pack, Empty> {
+        let mut state = self.state.lock().unwrap();
+        let now = secs_since(self.origin);
+        let request = request.0 as f64;
+        let spl = secs_per_liter(&state.rate);
+        let tolerance = self.full * spl;
+        let tat = state.tat.max(now);
+        let new_tat = tat + request * spl;
+        let allowed_at = new_tat - tolerance;
+        if !withdraw || allowed_at > now {
+            let retry_after = if request > self.full {
+                None
+            } else {
+                duration_of(allowed_at - now)
+            };
+            return Err(Empty::new(self.quota_at(&state, now), retry_after));
+        }
+        state.tat = new_tat;
+        let quota = self.quota_at(&state, now);
+        let next_refill = next_liter(quota.remaining().0 as f64, spl);
+        Ok(Pack::new(liters(request), quota, next_refill))
+    }
+}
+
+impl RateLimiter for Gcra {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Gcra
+    }
+
+    fn try_acquire(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>> {
+        let withdraw = self.queue.is_empty();
+        Box::pin(std::future::ready(self.acquire(liters, withdraw)))
+    }
+
+    fn acquire_waiting(
+        &self,
+        liters: Liters,
+        wait: Duration,
+    ) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(
+            self.queue
+                .acquire_waiting(wait, move |first| self.acquire(liters, first)),
+        )
+    }
+
+    fn quota(&self) -> BoxFuture<'_, Quota> {
+        let state = self.state.lock().unwrap();
+        let quota = self.quota_at(&state, secs_since(self.origin));
+        Box::pin(std::future::ready(quota))
+    }
+
+    fn fulfill(&self) -> BoxFuture<'_, ()> {
+        self.state.lock().unwrap().tat = secs_since(self.origin);
+        Box::pin(std::future::ready(()))
+    }
+
+    fn refill_rate(&self) -> RefillRate {
+        self.state.lock().unwrap().rate
+    }
+
+    fn set_refill_rate(&self, rate: RefillRate) {
+        let mut state = self.state.lock().unwrap();
+        let now = secs_since(self.origin);
+        // keep the liters owed, paid back at the new rate
+        let owed = (state.tat - now).max(0.0) / secs_per_liter(&state.rate);
+        state.tat = now + owed * secs_per_liter(&rate);
+        state.rate = rate;
+    }
+
+    fn is_idle(&self) -> BoxFuture<'_, bool> {
+        let idle = self.state.lock().unwrap().tat <= secs_since(self.origin);
+        Box::pin(std::future::ready(idle))
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio::time::Instant;
+
+use super::{duration_of, liters, next_liter, secs_per_liter, Algorithm, RateLimiter, WaitQueue};
+use crate::bucket::milk::{Empty, Pack, Quota, RefillRate};
+use crate::bucket::Liters;
+
+/// Token bucket refilled on access from the elapsed time, without a background task
+#[derive(Debug)]
+pub struct LazyBucket {
+    full: f64,
+    state: Mutex<State>,
+    queue: WaitQueue,
+}
+
+#[derive(Debug)]
+struct State {
+    filled: f64,
+    updated: Instant,
+    rate: RefillRate,
+}
+
+impl State {
+    fn settle(&mut self, full: f64) {
+        let now = Instant::now();
+        let refilled = (now - self.updated).as_secs_f64() / secs_per_liter(&self.rate);
+        self.filled = f64::min(self.filled + refilled, full);
+        self.updated = now;
+    }
+
+    fn quota(&self, full: f64) -> Quota {
+        let reset = duration_of((full - self.filled) * secs_per_liter(&self.rate));
+        Quota::new(liters(full), liters(self.filled), reset)
+    }
+}
+
+impl LazyBucket {
+    pub fn new(full: Liters, initial: Liters, rate: RefillRate) -> Self {
+        let state = State {
+            filled: initial.0 as f64,
+            updated: Instant::now(),
+            rate,
+        };
+        Self {
+            full: full.0 as f64,
+            state: Mutex::new(state),
+            queue: WaitQueue::default(),
+        }
+    }
+
+    /// Nothing is withdrawn unless `withdraw`, for someone waiting ahead
+    fn acquire(&self, request: Liters, withdraw: bool) -> Result<Pack, Empty> {
+        let mut state = self.state.lock().unwrap();
+        state.settle(self.full);
+        let request = request.0 as f64;
+        let spl = secs_per_liter(&state.rate);
+        if !withdraw || state.filled < request {
+            let retry_after = if request > self.full {
+                None
+            } else {
+                duration_of((request - state.filled) * spl)
+            };
+            return Err(Empty::new(state.quota(self.full), retry_after));
+        }
+        state.filled -= request;
+        let next_refill = next_liter(state.filled, spl);
+        Ok(Pack::new(
+            liters(request),
+            state.quota(self.full),
+            next_refill,
+        ))
+    }
+}
+
+impl RateLimiter for LazyBucket {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Lazy
+    }
+
+    fn try_acquire(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>> {
+        let withdraw = self.queue.is_empty();
+        Box::pin(std::future::ready(self.acquire(liters, withdraw)))
+    }
+
+    fn acquire_waiting(
+        &self,
+        liters: Liters,
+        wait: Duration,
+    ) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(
+            self.queue
+                .acquire_waiting(wait, move |first| self.acquire(liters, first)),
+        )
+    }
+
+    fn quota(&self) -> BoxFuture<'_, Quota> {
+        let mut state = self.state.lock().unwrap();
+        state.settle(self.full);
+        Box::pin(std::future::ready(state.quota(self.full)))
+    }
+
+    fn fulfill(&self) -> BoxFuture<'_, ()> {
+        let mut state = self.state.lock().unwrap();
+        state.filled = self.full;
+        state.updated = Instant::now();
+        Box::pin(std::future::ready(()))
+    }
+
+    fn refill_rate(&self) -> RefillRate {
+        self.state.lock().unwrap().rate
+    }
+
+    fn set_refill_rate(&self, rate: RefillRate) {
+        let mut state = self.state.lock().unwrap();
+        // refilled so far at the old rate
+        state.settle(self.full);
+        state.rate = rate;
+    }
+
+    fn is_idle(&self) -> BoxFuture<'_, bool> {
+        let mut state = self.state.lock().unwrap();
+        state.settle(self.full);
+        Box::pin(std::future::ready(state.filled >= self.full))
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio::time::Instant;
+
+use super::{duration_of, liters, secs_per_liter, secs_since, Algorithm, RateLimiter, WaitQueue};
+use crate::bucket::milk::{Empty, Pack, Quota, RefillRate};
+use crate::bucket::Liters;
+
+/// Log of withdrawals, allowing `full` liters within any window
+///
+/// The window is the time to refill `full` liters at the rate.
+#[derive(Debug)]
+pub struct SlidingLog {
+    full: f64,
+    origin: Instant,
+    state: Mutex<State>,
+    queue: WaitQueue,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Withdrawal {
+    /// seconds since `origin`
+    at: f64,
+    liters: f64,
+}
+
+#[derive(Debug)]
+struct State {
+    log: VecDeque<Withdrawal>,
+    rate: RefillRate,
+}
+
+impl State {
+    fn window(&self, full: f64) -> f64 {
+        full * secs_per_liter(&self.rate)
+    }
+
+    /// drop withdrawals out of the window, returning liters withdrawn within it
+    fn prune(&mut self, full: f64, now: f64) -> f64 {
+        let window = self.window(full);
+        while self.log.front().is_some_and(|w| w.at + window <= now) {
+            self.log.pop_front();
+        }
+        self.log.iter().map(|w| w.liters).sum()
+    }
+
+    /// until `liters` more are allowed, `None` if they never are
+    fn time_to_free(&self, full: f64, used: f64, liters: f64, now: f64) -> Option<f64> {
+        let window = self.window(full);
+        let mut freed = 0.0;
+        for w in &self.log {
+            freed += w.liters;
+            if used - freed + liters <= full {
+                return Some(w.at + window - now);
+            }
+        }
+        None
+    }
+
+    fn quota(&self, full: f64, used: f64, now: f64) -> Quota {
+        let window = self.window(full);
+        let reset = self.log.back().map_or(0.0, |w| w.at + window - now);
+        Quota::new(liters(full), liters(full - used), duration_of(reset))
+    }
+}
+
+impl SlidingLog {
+    /// `full` has to be positive, or the window is empty
+    pub fn new(full: Liters, initial: Liters, rate: RefillRate) -> Self {
+        let full_f = full.0 as f64;
+        let mut log = VecDeque::new();
+        let used = (full_f - initial.0 as f64).max(0.0);
+        if used > 0.0 {
+            log.push_back(Withdrawal {
+                at: 0.0,
+                liters: used,
+            });
+        }
+        Self {
+            full: full_f,
+            origin: Instant::now(),
+            state: Mutex::new(State { log, rate }),
+            queue: WaitQueue::default(),
+        }
+    }
+
+    /// Nothing is withdrawn unless `withdraw`, for someone waiting ahead
+    fn acquire(&self, request: Liters, withdraw: bool) -> Result<Pack, Empty> {
+        let mut state = self.state.lock().unwrap();
+        let now = secs_since(self.origin);
+        let used = state.prune(self.full, now);
+        let request = request.0 as f64;
+        if !withdraw || used + request > self.full {
+            let retry_after = if used + request <= self.full {
+                Some(Duration::ZERO)
+            } else {
+                state
+                    .time_to_free(self.full, used, request, now)
+                    .and_then(duration_of)
+            };
+            return Err(Empty::new(state.quota(self.full, used, now), retry_after));
+        }
+        state.log.push_back(Withdrawal {
+            at: now,
+            liters: request,
+        });
+        let used = used + request;
+        let window = state.window(self.full);
+        // until the oldest withdrawal leaves the window
+        let next_refill = state.log.front().map_or(0.0, |w| w.at + window - now);
+        Ok(Pack::new(
+            liters(request),
+            state.quota(self.full, used, now),
+            duration_of(next_refill).unwrap_or(std::time::Duration::MAX),
+        ))
+    }
+}
+
+impl RateLimiter for SlidingLog {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::SlidingLog
+    }
+
+    fn try_acquire(&self, liters: Liters) -> BoxFuture<'_, Result<Pack, Empty>> {
+        let withdraw = self.queue.is_empty();
+        Box::pin(std::future::ready(self.acquire(liters, withdraw)))
+    }
+
+    fn acquire_waiting(
+        &self,
+        liters: Liters,
+        wait: Duration,
+    ) -> BoxFuture<'_, Result<Pack, Empty>> {
+        Box::pin(
+            self.queue
+                .acquire_waiting(wait, move |first| self.acquire(liters, first)),
+        )
+    }
+
+    fn quota(&self) -> BoxFuture<'_, Quota> {
+        let mut state = self.state.lock().unwrap();
+        let now = secs_since(self.origin);
+        let used = state.prune(self.full, now);
+        Box::pin(std::future::ready(state.quota(self.full, used, now)))
+    }
+
+    fn fulfill(&self) -> BoxFuture<'_, ()> {
+        self.state.lock().unwrap().log.clear();
+        Box::pin(std::future::ready(()))
+    }
+
+    fn refill_rate(&self) -> RefillRate {
+        self.state.lock().unwrap().rate
+    }
+
+    /// changes the length of the window, the log is kept
+    fn set_refill_rate(&self, rate: RefillRate) {
+        self.state.lock().unwrap().rate = rate;
+    }
+
+    fn is_idle(&self) -> BoxFuture<'_, bool> {
+        let mut state = self.state.lock().unwrap();
+        let used = state.prune(self.full, secs_since(self.origin));
+        Box::pin(std::future::ready(used <= 0.0))
+    }
+}
@@ -1,19 +1,42 @@
+use std::collections::VecDeque;
 use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use tokio::sync::{watch, Mutex};
+use futures_util::Stream;
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::time::Instant;
 
-use super::{Liters, MilkBucket};
+use super::clock::{Clock, Interval, TokioClock};
+use super::level::{self, Change, Level};
+use super::{Liters, MilkBucket, Storage};
 
 // MARK: Inner
 
 #[derive(Debug)]
 pub(super) struct Inner {
     full: Liters,
-    filled: Mutex<Liters>,
+    clock: Arc<dyn Clock>,
+    level: Level,
+    /// creation of the bucket on `clock`
+    origin: Instant,
+    /// nanoseconds from `origin` to the last tick of [`MilkBucket::refill_task`],
+    /// or to when it (re)started ticking
+    ///
+    /// Set apart from the level, so it may be a tick behind a concurrent change.
+    /// It only estimates `next_refill` and the resets of [`Quota`].
+    refilled_at: AtomicU64,
     withdraw_tx: watch::Sender<()>,
     withdraw_rx: watch::Receiver<()>,
+    rate_tx: watch::Sender<RefillRate>,
+    events: std::sync::Mutex<EventLog>,
+    /// set by changes which were not logged as nobody was watching
+    unlogged: AtomicBool,
+    /// id of the latest event
+    event_tx: watch::Sender<u64>,
+    waiters_tx: watch::Sender<Waiters>,
 }
 
 // MARK: Builder
@@ -21,6 +44,9 @@ pub(super) struct Inner {
 pub struct Builder<Full = (), Initial = ()> {
     full: Full,
     initial: Initial,
+    rate: RefillRate,
+    clock: Arc<dyn Clock>,
+    storage: Storage,
 }
 
 impl Default for Builder {
@@ -28,6 +54,9 @@ impl Default for Builder {
         Self {
             full: (),
             initial: (),
+            rate: RefillRate::per_sec(Liters(1.0)),
+            clock: Arc::new(TokioClock),
+            storage: Storage::default(),
         }
     }
 }
@@ -46,32 +75,90 @@ impl MilkBucket {
 
 impl<Full, Initial> Builder<Full, Initial> {
     pub fn full(self, value: f32) -> Builder<Liters, Initial> {
-        let Self { initial, .. } = self;
+        let Self {
+            initial,
+            rate,
+            clock,
+            storage,
+            ..
+        } = self;
         Builder {
             full: Liters(value),
             initial,
+            rate,
+            clock,
+            storage,
         }
     }
 
     pub fn initial(self, value: f32) -> Builder<Full, Liters> {
-        let Self { full, .. } = self;
+        let Self {
+            full,
+            rate,
+            clock,
+            storage,
+            ..
+        } = self;
         Builder {
             full,
             initial: Liters(value),
+            rate,
+            clock,
+            storage,
+        }
+    }
+
+    /// 1 liter per second by default
+    pub fn refill_rate(self, value: RefillRate) -> Self {
+        Self {
+            rate: value,
+            ..self
+        }
+    }
+
+    /// [`TokioClock`] by default
+    pub fn clock(self, value: Arc<dyn Clock>) -> Self {
+        Self {
+            clock: value,
+            ..self
+        }
+    }
+
+    /// [`Storage::Atomic`] by default
+    pub fn storage(self, value: Storage) -> Self {
+        Self {
+            storage: value,
+            ..self
         }
     }
 }
 
 impl Builder<Liters, Liters> {
     pub fn build(self) -> MilkBucket {
-        let Self { full, initial } = self;
-        let filled = Mutex::new(initial);
+        let Self {
+            full,
+            initial,
+            rate,
+            clock,
+            storage,
+        } = self;
+        let origin = clock.now();
         let (tx, rx) = watch::channel(());
+        let (rate_tx, _) = watch::channel(rate);
+        let (event_tx, _) = watch::channel(0);
         let inner = Inner {
-            filled,
+            level: Level::new(storage, initial),
+            origin,
+            refilled_at: AtomicU64::new(0),
             full,
+            clock,
             withdraw_rx: rx,
             withdraw_tx: tx,
+            rate_tx,
+            events: std::sync::Mutex::new(EventLog::default()),
+            unlogged: AtomicBool::new(false),
+            event_tx,
+            waiters_tx: watch::Sender::new(Waiters::default()),
         };
         MilkBucket {
             inner: Arc::new(inner),
@@ -81,11 +168,43 @@ impl Builder<Liters, Liters> {
 
 // MARK: op with pack
 
-pub struct Pack(Liters);
+/// Snapshot of a bucket for rate limit headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    limit: Liters,
+    remaining: Liters,
+    /// until the bucket is full again, `None` if it is never refilled
+    reset: Option<Duration>,
+}
+
+/// Milk withdrawn by [`MilkBucket::try_withdraw`] or a [`RateLimiter`](super::limiter::RateLimiter)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pack {
+    liters: Liters,
+    quota: Quota,
+    next_refill: Duration,
+}
+
+/// Not enough milk in the bucket, nothing was withdrawn
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+#[error("No milk available")]
+pub struct Empty {
+    quota: Quota,
+    /// `None` if the bucket is never refilled enough
+    retry_after: Option<Duration>,
+}
 
 impl MilkBucket {
+    pub fn full(&self) -> Liters {
+        self.inner.full
+    }
+
     pub async fn available(&self) -> Liters {
-        *self.inner.filled.lock().await
+        self.inner.level.load().await
+    }
+
+    pub fn storage(&self) -> Storage {
+        self.inner.level.storage()
     }
 
     pub async fn is_empty(&self) -> bool {
@@ -101,38 +220,500 @@ impl MilkBucket {
         L: Into<Liters>,
     {
         let liters: Liters = liters.into();
-        let mut filled = self.inner.filled.lock().await;
-        let current = *filled;
-        let after = f32::min(current.0 + liters.0, self.inner.full.0);
-        *filled = Liters(after);
+        let full = self.inner.full;
+        let fill = |filled: Liters| Some(Liters(f32::min(filled.0 + liters.0, full.0)));
+        let publish = |change| self.publish(EventKind::Refill, change);
+        let _ = self.inner.level.update(fill, publish).await;
+    }
+
+    pub async fn quota(&self) -> Quota {
+        let rate = self.refill_rate();
+        let filled = self.available().await;
+        let next_refill = if filled.0 >= self.inner.full.0 {
+            rate.duration
+        } else {
+            rate.duration
+                .saturating_sub(self.elapsed_since(self.refilled_at()))
+        };
+        self.quota_of(rate, filled, next_refill)
+    }
+
+    fn quota_of(&self, rate: RefillRate, remaining: Liters, next_refill: Duration) -> Quota {
+        let full = self.inner.full;
+        let reset = if remaining.0 < full.0 {
+            rate.time_to_fill(remaining, full, full, next_refill)
+        } else {
+            Some(Duration::ZERO)
+        };
+        Quota {
+            limit: self.inner.full,
+            remaining,
+            reset,
+        }
     }
 
     pub async fn fulfill(&self) {
-        let mut filled = self.inner.filled.lock().await;
-        *filled = self.inner.full;
+        let full = self.inner.full;
+        let publish = |change| self.publish(EventKind::Fulfill, change);
+        let _ = self.inner.level.update(|_| Some(full), publish).await;
     }
 
+    /// Withdraw all of `request_liters` or nothing, in a single update of the level
+    ///
+    /// Fails while someone is waiting in [`Self::withdraw_waiting`], so that the queue is not jumped.
     #[tracing::instrument(skip(self))]
-    pub async fn withdraw_by(&self, request_liters: Liters) -> Pack {
-        let mut filled = self.inner.filled.lock().await;
-        let after = filled.0 - request_liters.0;
-        if after >= 0.0 {
-            tracing::info!(after, "milk withdrawn");
-            *filled = Liters(after);
-            if let Err(e) = self.inner.withdraw_tx.send(()) {
-                let err = &e as &dyn std::error::Error;
-                tracing::error!(err, "channel closed unexpectedly");
+    pub async fn try_withdraw(&self, request_liters: Liters) -> Result<Pack, Empty> {
+        let rate = self.refill_rate();
+        self.withdraw_now(rate, request_liters, false, false).await
+    }
+
+    /// [`Self::try_withdraw`] of all that is available if less than `request_liters`
+    #[tracing::instrument(skip(self))]
+    pub async fn try_withdraw_up_to(&self, request_liters: Liters) -> Result<Pack, Empty> {
+        let rate = self.refill_rate();
+        self.withdraw_now(rate, request_liters, true, false).await
+    }
+
+    /// [`Self::try_withdraw`], waiting up to `wait` in a FIFO queue until enough milk is refilled
+    ///
+    /// Dropping the future leaves the queue without withdrawing anything.
+    #[tracing::instrument(skip(self))]
+    pub async fn withdraw_waiting(
+        &self,
+        request_liters: Liters,
+        wait: Duration,
+    ) -> Result<Pack, Empty> {
+        if wait.is_zero() || request_liters.0 > self.inner.full.0 {
+            return self.try_withdraw(request_liters).await;
+        }
+        let deadline = self.inner.clock.now() + wait;
+        let mut queue_rx = self.inner.waiters_tx.subscribe();
+        let mut event_rx = self.inner.event_tx.subscribe();
+        let waiter = Waiter::enqueue(self).await;
+        loop {
+            queue_rx.mark_unchanged();
+            event_rx.mark_unchanged();
+            let rate = self.refill_rate();
+            let empty = if waiter.is_first() {
+                match self.withdraw_now(rate, request_liters, false, true).await {
+                    Ok(pack) => {
+                        drop(waiter);
+                        return Ok(pack);
+                    }
+                    Err(e) => e,
+                }
+            } else {
+                self.empty(self.available().await, rate, request_liters)
+            };
+            tokio::select! {
+                _ = self.inner.clock.sleep_until(deadline) => {
+                    tracing::info!("gave up waiting for milk");
+                    return Err(empty);
+                }
+                _ = queue_rx.changed() => {}
+                _ = event_rx.changed() => {}
+            }
+        }
+    }
+
+    fn empty(&self, filled: Liters, rate: RefillRate, request_liters: Liters) -> Empty {
+        let next_refill = rate
+            .duration
+            .saturating_sub(self.elapsed_since(self.refilled_at()));
+        let retry_after = rate.time_to_fill(filled, request_liters, self.inner.full, next_refill);
+        Empty {
+            quota: self.quota_of(rate, filled, next_refill),
+            retry_after,
+        }
+    }
+
+    /// `partial` to take what is available if less than `request_liters`
+    ///
+    /// Nothing is withdrawn while someone is waiting, unless `first` in the queue. The queue is
+    /// checked within the update of the level, which is ordered with joining the queue.
+    async fn withdraw_now(
+        &self,
+        rate: RefillRate,
+        request_liters: Liters,
+        partial: bool,
+        first: bool,
+    ) -> Result<Pack, Empty> {
+        // delivered as deducted from the level
+        let request_liters = level::whole_millilitres(request_liters);
+        let withdraw = |filled: Liters| {
+            if !first && !self.inner.waiters_tx.borrow().queue.is_empty() {
+                tracing::info!("someone is waiting for milk");
+                return None;
+            }
+            let taken = if partial {
+                f32::min(request_liters.0, filled.0)
+            } else {
+                request_liters.0
+            };
+            let after = filled.0 - taken;
+            (after >= 0.0 && (taken > 0.0 || !partial)).then_some(Liters(after))
+        };
+        let publish = |change| self.publish(EventKind::Withdraw, change);
+        let (before, after) = match self.inner.level.update(withdraw, publish).await {
+            Ok(Change { before, after, .. }) => (before, after),
+            Err(filled) => {
+                tracing::info!(available = filled.0, "not enough milk");
+                return Err(self.empty(filled, rate, request_liters));
             }
-            Pack(request_liters)
+        };
+        let now = self.inner.clock.now();
+        let next_refill = if before.0 >= self.inner.full.0 {
+            // refill_task is idle and starts ticking again from now
+            self.set_refilled_at(now);
+            rate.duration
         } else {
-            Pack(Liters(0.0))
+            rate.duration
+                .saturating_sub(now.saturating_duration_since(self.refilled_at()))
+        };
+        tracing::info!(after = after.0, "milk withdrawn");
+        if let Err(e) = self.inner.withdraw_tx.send(()) {
+            let err = &e as &dyn std::error::Error;
+            tracing::error!(err, "channel closed unexpectedly");
         }
+        let liters = if partial {
+            Liters(f32::min(request_liters.0, before.0))
+        } else {
+            request_liters
+        };
+        Ok(Pack {
+            liters,
+            quota: self.quota_of(rate, after, next_refill),
+            next_refill,
+        })
+    }
+
+    async fn refill_tick(&self, liters: Liters) {
+        let full = self.inner.full;
+        let fill = |filled: Liters| Some(Liters(f32::min(filled.0 + liters.0, full.0)));
+        let publish = |change: Change| {
+            if change.before != change.after {
+                self.publish(EventKind::Refill, change);
+            }
+        };
+        let _ = self.inner.level.update(fill, publish).await;
+        self.restart_refill_clock();
+    }
+
+    fn restart_refill_clock(&self) {
+        self.set_refilled_at(self.inner.clock.now());
+    }
+
+    fn refilled_at(&self) -> Instant {
+        let nanos = self.inner.refilled_at.load(Ordering::Acquire);
+        self.inner.origin + Duration::from_nanos(nanos)
+    }
+
+    fn set_refilled_at(&self, at: Instant) {
+        let nanos = at.saturating_duration_since(self.inner.origin).as_nanos();
+        self.inner
+            .refilled_at
+            .store(u64::try_from(nanos).unwrap_or(u64::MAX), Ordering::Release);
+    }
+
+    fn elapsed_since(&self, earlier: Instant) -> Duration {
+        self.inner.clock.now().saturating_duration_since(earlier)
     }
 }
 
 impl Pack {
+    pub(super) fn new(liters: Liters, quota: Quota, next_refill: Duration) -> Self {
+        Self {
+            liters,
+            quota,
+            next_refill,
+        }
+    }
+
     pub fn inner(self) -> Liters {
-        self.0
+        self.liters
+    }
+
+    /// level of the bucket right after the withdrawal
+    pub fn remaining(&self) -> Liters {
+        self.quota.remaining
+    }
+
+    pub fn quota(&self) -> &Quota {
+        &self.quota
+    }
+
+    /// until the next tick of the refill task
+    pub fn next_refill(&self) -> Duration {
+        self.next_refill
+    }
+}
+
+impl Empty {
+    pub(super) fn new(quota: Quota, retry_after: Option<Duration>) -> Self {
+        Self { quota, retry_after }
+    }
+
+    pub fn available(&self) -> Liters {
+        self.quota.remaining
+    }
+
+    pub fn quota(&self) -> &Quota {
+        &self.quota
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+}
+
+impl Quota {
+    pub(super) fn new(limit: Liters, remaining: Liters, reset: Option<Duration>) -> Self {
+        Self {
+            limit,
+            remaining,
+            reset,
+        }
+    }
+
+    pub fn limit(&self) -> Liters {
+        self.limit
+    }
+
+    pub fn remaining(&self) -> Liters {
+        self.remaining
+    }
+
+    pub fn reset(&self) -> Option<Duration> {
+        self.reset
+    }
+}
+
+// MARK: waiters
+
+#[derive(Debug, Default)]
+struct Waiters {
+    next_ticket: u64,
+    queue: VecDeque<u64>,
+}
+
+/// Place in the queue of [`MilkBucket::withdraw_waiting`], left on drop
+struct Waiter<'a> {
+    bucket: &'a MilkBucket,
+    ticket: u64,
+}
+
+impl<'a> Waiter<'a> {
+    /// in order with the updates of the level, so that no withdrawal after it misses the queue
+    async fn enqueue(bucket: &'a MilkBucket) -> Self {
+        let join = || {
+            let mut ticket = 0;
+            bucket.inner.waiters_tx.send_modify(|w| {
+                ticket = w.next_ticket;
+                w.next_ticket += 1;
+                w.queue.push_back(ticket);
+            });
+            ticket
+        };
+        let ticket = bucket.inner.level.order(join).await;
+        Self { bucket, ticket }
+    }
+
+    fn is_first(&self) -> bool {
+        self.bucket.inner.waiters_tx.borrow().queue.front() == Some(&self.ticket)
+    }
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        let ticket = self.ticket;
+        self.bucket
+            .inner
+            .waiters_tx
+            .send_modify(|w| w.queue.retain(|t| *t != ticket));
+    }
+}
+
+impl MilkBucket {
+    /// number of requests in [`Self::withdraw_waiting`]
+    pub fn waiting(&self) -> usize {
+        self.inner.waiters_tx.borrow().queue.len()
+    }
+}
+
+// MARK: events
+
+/// recent events kept for `Last-Event-ID` resumption
+const EVENT_LOG_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// snapshot of the level, sent when nothing can be replayed
+    Level,
+    Withdraw,
+    Refill,
+    Fulfill,
+}
+
+/// Level of a bucket right after a change
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LevelEvent {
+    #[serde(skip)]
+    id: u64,
+    kind: EventKind,
+    level: Liters,
+    full: Liters,
+}
+
+#[derive(Debug, Default)]
+struct EventLog {
+    /// ids start from 1, 0 means nothing was published yet
+    last_id: u64,
+    events: VecDeque<LevelEvent>,
+    /// [`Change::seq`] and level of the latest change published
+    latest: Option<(u32, Liters)>,
+}
+
+impl EventLog {
+    fn push(&mut self, kind: EventKind, level: Liters, full: Liters) {
+        self.last_id += 1;
+        if self.events.len() == EVENT_LOG_CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(LevelEvent {
+            id: self.last_id,
+            kind,
+            level,
+            full,
+        });
+    }
+
+    /// Drop every event, so that resuming from any of them starts from a snapshot
+    fn forget(&mut self) {
+        self.last_id += 1;
+        self.events.clear();
+        self.latest = None;
+    }
+
+    /// events after `id`, or `None` if some of them were already dropped
+    fn since(&self, id: u64) -> Option<Vec<LevelEvent>> {
+        if id > self.last_id {
+            return None;
+        }
+        let oldest = self.events.front().map_or(self.last_id + 1, |e| e.id);
+        if id + 1 < oldest {
+            return None;
+        }
+        let events = self.events.iter().filter(|e| e.id > id).copied().collect();
+        Some(events)
+    }
+}
+
+impl LevelEvent {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn kind(&self) -> EventKind {
+        self.kind
+    }
+
+    pub fn level(&self) -> Liters {
+        self.level
+    }
+}
+
+impl MilkBucket {
+    /// To be called right after the change of the level, from [`Level::update`]
+    ///
+    /// Concurrent changes of an atomic level may be published out of order. Every event has the
+    /// level right after its own change, and a late one is followed by a [`EventKind::Level`]
+    /// of the latest change, so that the last event always has the current level.
+    ///
+    /// Nothing is logged while no stream is open, and the next one starts from a snapshot.
+    fn publish(&self, kind: EventKind, change: Change) {
+        if !self.is_watched() {
+            self.inner.unlogged.store(true, Ordering::SeqCst);
+            // a stream opened meanwhile may have missed the flag, but not this change
+            if !self.is_watched() {
+                return;
+            }
+        }
+        let mut log = self.inner.events.lock().unwrap();
+        self.forget_unlogged(&mut log);
+        log.push(kind, change.after, self.inner.full);
+        match log.latest {
+            Some((seq, level)) if !change.follows(seq) => {
+                log.push(EventKind::Level, level, self.inner.full);
+            }
+            _ => log.latest = Some((change.seq(), change.after)),
+        }
+        self.inner.event_tx.send_replace(log.last_id);
+    }
+
+    /// Forget the log if it missed some changes, which cannot be replayed
+    fn forget_unlogged(&self, log: &mut EventLog) {
+        if self.inner.unlogged.swap(false, Ordering::SeqCst) {
+            log.forget();
+        }
+    }
+
+    /// whether any [`Self::level_events`] stream is open
+    pub fn is_watched(&self) -> bool {
+        self.inner.event_tx.receiver_count() > 0
+    }
+
+    /// events after `last_id` if they are all kept, otherwise a [`EventKind::Level`] snapshot
+    async fn events_since(&self, last_id: Option<u64>) -> Vec<LevelEvent> {
+        let level = self.available().await;
+        let mut log = self.inner.events.lock().unwrap();
+        self.forget_unlogged(&mut log);
+        if let Some(events) = last_id.and_then(|id| log.since(id)) {
+            return events;
+        }
+        // changes published later come with later ids
+        let level = log.latest.map_or(level, |(_, l)| l);
+        vec![LevelEvent {
+            id: log.last_id,
+            kind: EventKind::Level,
+            level,
+            full: self.inner.full,
+        }]
+    }
+
+    /// Level changes from `last_id` on, starting with a snapshot if it is `None` or too old
+    pub fn level_events(self, last_id: Option<u64>) -> impl Stream<Item = LevelEvent> + Send {
+        struct State {
+            bucket: MilkBucket,
+            rx: watch::Receiver<u64>,
+            last_id: Option<u64>,
+            pending: VecDeque<LevelEvent>,
+            started: bool,
+        }
+
+        let rx = self.inner.event_tx.subscribe();
+        let state = State {
+            bucket: self,
+            rx,
+            last_id,
+            pending: VecDeque::new(),
+            started: false,
+        };
+        futures_util::stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.pending.pop_front() {
+                    state.last_id = Some(event.id);
+                    return Some((event, state));
+                }
+                if state.started {
+                    state.rx.changed().await.ok()?;
+                }
+                state.started = true;
+                state.rx.mark_unchanged();
+                let events = state.bucket.events_since(state.last_id).await;
+                state.pending.extend(events);
+            }
+        })
     }
 }
 
@@ -146,20 +727,43 @@ pub struct RefillRate {
 }
 
 impl MilkBucket {
+    pub fn refill_rate(&self) -> RefillRate {
+        *self.inner.rate_tx.borrow()
+    }
+
+    /// Takes effect on the running [`Self::refill_task`]
+    pub fn set_refill_rate(&self, rate: RefillRate) {
+        self.inner.rate_tx.send_replace(rate);
+    }
+
     #[tracing::instrument(skip(self))]
-    pub fn refill_task(self, rate: RefillRate) -> impl Future<Output = ()> + Send + 'static {
-        let RefillRate { amount, duration } = rate;
+    pub fn refill_task(self) -> impl Future<Output = ()> + Send + 'static {
         let mut rx = self.inner.withdraw_rx.clone();
+        let mut rate_rx = self.inner.rate_tx.subscribe();
         async move {
             loop {
-                let mut interval = tokio::time::interval(duration);
-                interval.tick().await; // ignore immediate tick
-                while !self.is_full().await {
-                    interval.tick().await;
-                    self.fill_by(amount).await;
-                    tracing::debug!("tick");
+                let RefillRate { amount, duration } = *rate_rx.borrow_and_update();
+                let mut interval = Interval::new(Arc::clone(&self.inner.clock), duration);
+                self.restart_refill_clock();
+                let mut rate_changed = false;
+                while !rate_changed && !self.is_full().await {
+                    tokio::select! {
+                        _ = interval.tick() => {
+                            self.refill_tick(amount).await;
+                            tracing::debug!("tick");
+                        }
+                        _ = rate_rx.changed() => rate_changed = true,
+                    }
                 }
-                let Err(err) = rx.changed().await else {
+                if rate_changed {
+                    tracing::info!(rate = ?*rate_rx.borrow(), "refill rate changed");
+                    continue;
+                }
+                let changed = tokio::select! {
+                    r = rx.changed() => r,
+                    r = rate_rx.changed() => r,
+                };
+                let Err(err) = changed else {
                     continue;
                 };
                 let err = &err as &dyn std::error::Error;
@@ -178,4 +782,39 @@ impl RefillRate {
     pub fn per_sec(amount: Liters) -> Self {
         Self::new(amount, Duration::from_secs(1))
     }
+
+    pub fn amount(&self) -> Liters {
+        self.amount
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// liters refilled by the ticks within `elapsed`, ignoring the capacity
+    pub fn refilled_in(&self, elapsed: Duration) -> Liters {
+        if self.duration.is_zero() {
+            return Liters(0.0);
+        }
+        let ticks = (elapsed.as_secs_f64() / self.duration.as_secs_f64()).floor();
+        Liters((ticks * self.amount.0 as f64) as f32)
+    }
+
+    /// time until a bucket of `full` is refilled from `from` up to `to`, given the first tick
+    /// comes after `next_tick`
+    ///
+    /// `None` if it never is, because no milk is refilled or `to` is above `full`.
+    fn time_to_fill(
+        &self,
+        from: Liters,
+        to: Liters,
+        full: Liters,
+        next_tick: Duration,
+    ) -> Option<Duration> {
+        if self.amount.0 <= 0.0 || to.0 > full.0 {
+            return None;
+        }
+        let ticks = ((to.0 - from.0) / self.amount.0).ceil().max(1.0) as u32;
+        Some(next_tick + self.duration * (ticks - 1))
+    }
 }
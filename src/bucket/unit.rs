@@ -1,23 +1,436 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
 use serde::{Deserialize, Serialize};
 
-// MARK: US units
+// MARK: registry
+
+/// Unit of volume, converted through liters in `f64`
+#[derive(Debug, PartialEq)]
+pub struct VolumeUnit {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    /// liters in one of this unit
+    liters: Decimal,
+}
+
+/// `.0 * 10^-.1`, exact unlike `f64`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Decimal(u64, u32);
+
+impl Decimal {
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / 10f64.powi(self.1 as i32)
+    }
+
+    fn to_ratio(self) -> BigRational {
+        BigRational::new(self.0.into(), BigInt::from(10).pow(self.1))
+    }
+}
+
+// https://www.unitconverters.net/volume-converter.html
+pub const MILLILITERS: VolumeUnit = VolumeUnit {
+    name: "milliliters",
+    aliases: &["ml", "millilitres"],
+    liters: Decimal(1, 3),
+};
+
+pub const LITERS: VolumeUnit = VolumeUnit {
+    name: "liters",
+    aliases: &["l"],
+    liters: Decimal(1, 0),
+};
+
+pub const LITRES: VolumeUnit = VolumeUnit {
+    name: "litres",
+    aliases: &[],
+    liters: Decimal(1, 0),
+};
+
+// US customary units
+
+pub const TEASPOONS: VolumeUnit = VolumeUnit {
+    name: "teaspoons",
+    aliases: &["tsp"],
+    liters: Decimal(492_892_159_375, 14),
+};
+
+pub const TABLESPOONS: VolumeUnit = VolumeUnit {
+    name: "tablespoons",
+    aliases: &["tbsp"],
+    liters: Decimal(1_478_676_478_125, 14),
+};
+
+pub const FLUID_OUNCES: VolumeUnit = VolumeUnit {
+    name: "fluid_ounces",
+    aliases: &["fl_oz", "us_fluid_ounces"],
+    liters: Decimal(295_735_295_625, 13),
+};
+
+pub const CUPS: VolumeUnit = VolumeUnit {
+    name: "cups",
+    aliases: &["cup", "us_cups"],
+    liters: Decimal(2_365_882_365, 10),
+};
+
+pub const US_PINTS: VolumeUnit = VolumeUnit {
+    name: "us_pints",
+    aliases: &[],
+    liters: Decimal(473_176_473, 9),
+};
+
+pub const QUARTS: VolumeUnit = VolumeUnit {
+    name: "quarts",
+    aliases: &["qt", "us_quarts"],
+    liters: Decimal(946_352_946, 9),
+};
+
+pub const GALLONS: VolumeUnit = VolumeUnit {
+    name: "gallons",
+    aliases: &["gal", "us_gallons"],
+    liters: Decimal(3_785_411_784, 9),
+};
+
+// imperial units
+
+pub const IMPERIAL_FLUID_OUNCES: VolumeUnit = VolumeUnit {
+    name: "imperial_fluid_ounces",
+    aliases: &["imp_fl_oz"],
+    liters: Decimal(284_130_625, 10),
+};
+
+/// imperial pints, as in the UK
+pub const PINTS: VolumeUnit = VolumeUnit {
+    name: "pints",
+    aliases: &["imperial_pints"],
+    liters: Decimal(56_826_125, 8),
+};
+
+pub const IMPERIAL_QUARTS: VolumeUnit = VolumeUnit {
+    name: "imperial_quarts",
+    aliases: &[],
+    liters: Decimal(11_365_225, 7),
+};
+
+pub const IMPERIAL_GALLONS: VolumeUnit = VolumeUnit {
+    name: "imperial_gallons",
+    aliases: &["imp_gal"],
+    liters: Decimal(454_609, 5),
+};
+
+pub static UNITS: [&VolumeUnit; 14] = [
+    &MILLILITERS,
+    &LITERS,
+    &LITRES,
+    &TEASPOONS,
+    &TABLESPOONS,
+    &FLUID_OUNCES,
+    &CUPS,
+    &US_PINTS,
+    &QUARTS,
+    &GALLONS,
+    &IMPERIAL_FLUID_OUNCES,
+    &PINTS,
+    &IMPERIAL_QUARTS,
+    &IMPERIAL_GALLONS,
+];
+
+impl VolumeUnit {
+    /// unit of the name or an alias of it
+    pub fn find(name: &str) -> Option<&'static Self> {
+        UNITS
+            .into_iter()
+            .find(|u| u.name == name || u.aliases.contains(&name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    pub fn to_liters(&self, value: f64) -> f64 {
+        value * self.liters.to_f64()
+    }
+
+    pub fn from_liters(&self, liters: f64) -> f64 {
+        liters / self.liters.to_f64()
+    }
+
+    pub fn convert(&self, value: f64, to: &Self) -> f64 {
+        to.from_liters(self.to_liters(value))
+    }
+
+    /// [`Self::convert`] in rational numbers, rounded once at the end
+    ///
+    /// `value` is taken as the shortest decimal which reads back to it, such as `0.1`.
+    /// Non-finite values are converted as floats. `None` if the rounded decimal has more
+    /// significant digits than `f64` keeps, as it would not be exact.
+    pub fn convert_exact(&self, value: f64, to: &Self, exact: Exact) -> Option<f64> {
+        match parse_decimal(&value.to_string()) {
+            Some(v) => exact.round(self.convert_ratio(v, to)),
+            None => Some(self.convert(value, to)),
+        }
+    }
+
+    fn convert_ratio(&self, value: BigRational, to: &Self) -> BigRational {
+        value * self.liters.to_ratio() / to.liters.to_ratio()
+    }
+}
+
+// MARK: exact conversion
+
+/// How [`Exact`] rounds to its precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// to the nearest, ties to the even neighbour
+    #[default]
+    HalfEven,
+    /// to the nearest, ties away from zero
+    HalfUp,
+    /// toward zero
+    Truncate,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("unknown rounding {0:?}, expected `half_even`, `half_up` or `truncate`")]
+pub struct InvalidRounding(String);
+
+impl std::str::FromStr for Rounding {
+    type Err = InvalidRounding;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('-', "_").as_str() {
+            "half_even" => Ok(Self::HalfEven),
+            "half_up" => Ok(Self::HalfUp),
+            "truncate" => Ok(Self::Truncate),
+            _ => Err(InvalidRounding(s.to_string())),
+        }
+    }
+}
+
+/// Rounding of exact conversions to `precision` decimal places
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exact {
+    precision: u32,
+    rounding: Rounding,
+}
+
+impl Exact {
+    /// more decimal places than `f64` keeps are rejected
+    pub const MAX_PRECISION: u32 = f64::DIGITS;
+
+    pub fn new(precision: u32, rounding: Rounding) -> Option<Self> {
+        (precision <= Self::MAX_PRECISION).then_some(Self {
+            precision,
+            rounding,
+        })
+    }
+
+    pub fn precision(&self) -> u32 {
+        self.precision
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    /// the rounded decimal as the `f64` which prints as it
+    ///
+    /// `None` beyond [`f64::DIGITS`] significant digits, where no such `f64` may exist.
+    fn round(&self, value: BigRational) -> Option<f64> {
+        let scaled = value * BigRational::from_integer(BigInt::from(10).pow(self.precision));
+        let rounded = match self.rounding {
+            Rounding::HalfEven => round_half_even(scaled),
+            Rounding::HalfUp => scaled.round(),
+            Rounding::Truncate => scaled.trunc(),
+        };
+        let rounded = rounded.to_integer();
+        let significant = rounded.abs().to_string().trim_end_matches('0').len();
+        if significant > f64::DIGITS as usize {
+            return None;
+        }
+        let sign = if rounded.is_negative() { "-" } else { "" };
+        let digits = format!(
+            "{:0>width$}",
+            rounded.abs(),
+            width = self.precision as usize + 1
+        );
+        let (int, frac) = digits.split_at(digits.len() - self.precision as usize);
+        format!("{sign}{int}.{frac}").parse().ok()
+    }
+}
+
+fn round_half_even(value: BigRational) -> BigRational {
+    let floor = value.floor();
+    let half = BigRational::new(1.into(), 2.into());
+    match (&value - &floor).cmp(&half) {
+        Ordering::Less => floor,
+        Ordering::Greater => floor + BigInt::one(),
+        Ordering::Equal if (floor.to_integer() % BigInt::from(2)).is_zero() => floor,
+        Ordering::Equal => floor + BigInt::one(),
+    }
+}
+
+/// `-12.345` as a rational, `None` for anything but plain decimals
+fn parse_decimal(s: &str) -> Option<BigRational> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    let numer: BigInt = format!("{int}{frac}").parse().ok()?;
+    let denom = BigInt::from(10).pow(u32::try_from(frac.len()).ok()?);
+    Some(BigRational::new(numer, denom))
+}
+
+/// `value` as the `f64` which prints the same, without the digits `as f64` makes up
+pub fn widen(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(value as f64)
+}
+
+/// Value in a [`VolumeUnit`], as `{"<unit>": <value>}` in JSON
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "BTreeMap<String, f64>", into = "BTreeMap<String, f64>")]
+pub struct Quantity {
+    /// name of the unit as requested, which may be an alias
+    name: String,
+    unit: &'static VolumeUnit,
+    value: f64,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum InvalidQuantity {
+    #[error("expected exactly one unit, found {0}")]
+    Count(usize),
+    #[error("unknown unit {0:?}")]
+    UnknownUnit(String),
+    #[error("no unit to convert {0:?} to by default")]
+    NoPartner(String),
+    #[error("{0:?} is too large to convert")]
+    Overflow(String),
+    #[error("{0:?} converted has more significant digits than kept exactly")]
+    Inexact(String),
+}
+
+impl TryFrom<BTreeMap<String, f64>> for Quantity {
+    type Error = InvalidQuantity;
+
+    fn try_from(value: BTreeMap<String, f64>) -> Result<Self, Self::Error> {
+        if value.len() != 1 {
+            return Err(InvalidQuantity::Count(value.len()));
+        }
+        let (name, value) = value.into_iter().next().unwrap();
+        let unit = VolumeUnit::find(&name).ok_or(InvalidQuantity::UnknownUnit(name.clone()))?;
+        Ok(Self { name, unit, value })
+    }
+}
+
+impl From<Quantity> for BTreeMap<String, f64> {
+    fn from(value: Quantity) -> Self {
+        BTreeMap::from([(value.name, value.value)])
+    }
+}
+
+impl Quantity {
+    pub fn new(unit: &'static VolumeUnit, value: f64) -> Self {
+        Self {
+            name: unit.name.to_string(),
+            unit,
+            value,
+        }
+    }
+
+    /// `value` in the same unit, named as this one
+    pub fn with_value(&self, value: f64) -> Self {
+        Self {
+            name: self.name.clone(),
+            unit: self.unit,
+            value,
+        }
+    }
+
+    /// as requested, which may be an alias of [`Self::unit`]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn unit(&self) -> &'static VolumeUnit {
+        self.unit
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// in the unit named `name`, which is kept as is in JSON, exactly if `exact` is set
+    ///
+    /// Fails rather than giving infinity when the value is too large for the unit.
+    pub fn convert_to(&self, name: &str, exact: Option<Exact>) -> Result<Self, InvalidQuantity> {
+        let unit =
+            VolumeUnit::find(name).ok_or_else(|| InvalidQuantity::UnknownUnit(name.to_string()))?;
+        let value = match exact {
+            Some(exact) => self
+                .unit
+                .convert_exact(self.value, unit, exact)
+                .ok_or_else(|| InvalidQuantity::Inexact(self.name.clone()))?,
+            None => self.unit.convert(self.value, unit),
+        };
+        if !value.is_finite() {
+            return Err(InvalidQuantity::Overflow(self.name.clone()));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            unit,
+            value,
+        })
+    }
+}
 
-// https://www.unitconverters.net/volume/gallons-to-liters.htm
-#[allow(clippy::excessive_precision)]
-const LITER_PER_GALLON: f32 = 3.785411784;
+/// Newtype of a value in a [`VolumeUnit`] of the registry
+pub trait Volume: Copy {
+    const UNIT: &'static VolumeUnit;
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Deserialize, Serialize)]
-#[serde(transparent)]
-pub struct Liters(pub f32);
+    fn from_value(value: f32) -> Self;
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Deserialize, Serialize)]
-#[serde(transparent)]
-pub struct Gallons(pub f32);
+    fn value(self) -> f32;
+
+    fn convert<V: Volume>(self) -> V {
+        let value = Self::UNIT.convert(self.value() as f64, V::UNIT);
+        V::from_value(value as f32)
+    }
+}
+
+macro_rules! volume {
+    ($name:ident, $unit:expr) => {
+        #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Deserialize, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(pub f32);
+
+        impl Volume for $name {
+            const UNIT: &'static VolumeUnit = &$unit;
+
+            fn from_value(value: f32) -> Self {
+                Self(value)
+            }
+
+            fn value(self) -> f32 {
+                self.0
+            }
+        }
+    };
+}
+
+// MARK: US units
+
+volume!(Liters, LITERS);
+volume!(Gallons, GALLONS);
 
 impl Liters {
     pub fn gallons(self) -> Gallons {
-        let g = self.0 / LITER_PER_GALLON;
-        Gallons(g)
+        self.convert()
     }
 }
 
@@ -29,8 +442,7 @@ impl From<Gallons> for Liters {
 
 impl Gallons {
     pub fn liters(self) -> Liters {
-        let l = self.0 * LITER_PER_GALLON;
-        Liters(l)
+        self.convert()
     }
 }
 
@@ -42,21 +454,12 @@ impl From<Liters> for Gallons {
 
 // MARK: UK units
 
-// https://www.unitconverters.net/volume/pint-uk-to-liter.htm
-const LITRE_PER_UK_PINT: f32 = 0.56826125;
-
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Deserialize, Serialize)]
-#[serde(transparent)]
-pub struct Litres(pub f32);
-
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Deserialize, Serialize)]
-#[serde(transparent)]
-pub struct Pints(pub f32);
+volume!(Litres, LITRES);
+volume!(Pints, PINTS);
 
 impl Litres {
     pub fn pints(self) -> Pints {
-        let p = self.0 / LITRE_PER_UK_PINT;
-        Pints(p)
+        self.convert()
     }
 }
 
@@ -68,8 +471,7 @@ impl From<Pints> for Litres {
 
 impl Pints {
     pub fn litres(self) -> Litres {
-        let l = self.0 * LITRE_PER_UK_PINT;
-        Litres(l)
+        self.convert()
     }
 }
 
@@ -78,3 +480,111 @@ impl From<Litres> for Pints {
         value.pints()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(numer.into(), denom.into())
+    }
+
+    fn exact(precision: u32, rounding: Rounding) -> Exact {
+        Exact::new(precision, rounding).unwrap()
+    }
+
+    #[test]
+    fn round_half_even_breaks_ties_to_even() {
+        for (value, expected) in [
+            (ratio(5, 2), 2),
+            (ratio(7, 2), 4),
+            (ratio(1, 2), 0),
+            (ratio(-5, 2), -2),
+            (ratio(-7, 2), -4),
+            (ratio(12, 5), 2),
+            (ratio(13, 5), 3),
+            (ratio(-13, 5), -3),
+            (ratio(3, 1), 3),
+        ] {
+            let rounded = round_half_even(value.clone());
+            assert_eq!(rounded, ratio(expected, 1), "{value}");
+        }
+    }
+
+    #[test]
+    fn parse_decimal_reads_plain_decimals() {
+        assert_eq!(parse_decimal("-12.345"), Some(ratio(-12345, 1000)));
+        assert_eq!(parse_decimal("0.1"), Some(ratio(1, 10)));
+        assert_eq!(parse_decimal("-0.5"), Some(ratio(-1, 2)));
+        assert_eq!(parse_decimal("42"), Some(ratio(42, 1)));
+        for s in ["", "inf", "NaN", "1e5", "1.2.3", "0x10"] {
+            assert_eq!(parse_decimal(s), None, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn rounding_modes_round_to_the_precision() {
+        for (value, rounding, expected) in [
+            (0.125, Rounding::HalfEven, 0.12),
+            (0.135, Rounding::HalfEven, 0.14),
+            (0.125, Rounding::HalfUp, 0.13),
+            (0.129, Rounding::Truncate, 0.12),
+            (-0.125, Rounding::HalfEven, -0.12),
+            (-0.125, Rounding::HalfUp, -0.13),
+            (-0.129, Rounding::Truncate, -0.12),
+        ] {
+            let converted = LITERS.convert_exact(value, &LITRES, exact(2, rounding));
+            assert_eq!(converted, Some(expected), "{value} {rounding:?}");
+        }
+    }
+
+    #[test]
+    fn convert_exact_avoids_float_error() {
+        let cups = MILLILITERS.convert_exact(236.5882365, &CUPS, exact(15, Rounding::HalfEven));
+        assert_eq!(cups, Some(1.0));
+        assert_ne!(LITERS.convert(0.7, &MILLILITERS), 700.0);
+        let ml = LITERS.convert_exact(0.7, &MILLILITERS, exact(0, Rounding::HalfEven));
+        assert_eq!(ml, Some(700.0));
+        let gallons = LITERS.convert_exact(1.0, &GALLONS, exact(6, Rounding::HalfEven));
+        assert_eq!(gallons, Some(0.264172));
+        let pints = IMPERIAL_GALLONS.convert_exact(1.0, &PINTS, exact(3, Rounding::Truncate));
+        assert_eq!(pints, Some(8.0));
+    }
+
+    #[test]
+    fn convert_exact_bounds_significant_digits() {
+        let full = exact(Exact::MAX_PRECISION, Rounding::HalfEven);
+        // 4.226752837730375... needs 16
+        assert_eq!(LITERS.convert_exact(1.0, &CUPS, full), None);
+        assert!(LITERS
+            .convert_exact(1.0, &CUPS, exact(14, Rounding::HalfEven))
+            .is_some());
+        // trailing zeros are free
+        assert_eq!(LITERS.convert_exact(1e20, &MILLILITERS, full), Some(1e23));
+        assert_eq!(
+            Exact::new(Exact::MAX_PRECISION + 1, Rounding::HalfEven),
+            None
+        );
+    }
+
+    #[test]
+    fn convert_exact_results_print_as_the_rounded_decimal() {
+        for unit in UNITS {
+            for to in UNITS {
+                for precision in [0, 3, 9] {
+                    let exact = exact(precision, Rounding::HalfEven);
+                    let Some(value) = unit.convert_exact(1.75, to, exact) else {
+                        continue;
+                    };
+                    let printed = parse_decimal(&value.to_string()).unwrap();
+                    let expected = exact.round(unit.convert_ratio(ratio(7, 4), to)).unwrap();
+                    assert_eq!(value, expected);
+                    // converting the printed value is stable
+                    let scale = BigRational::from_integer(BigInt::from(10).pow(precision));
+                    assert!((printed * scale).is_integer(), "{value}");
+                    assert_eq!(to.convert_exact(value, to, exact), Some(value));
+                }
+            }
+        }
+    }
+}
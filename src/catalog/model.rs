@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+#[must_use]
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct ItemName(pub String);
+
+impl ItemName {
+    pub const COLUMN_NAME: &str = "name";
+}
+
+#[must_use]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Stock(pub i64);
+
+impl Stock {
+    pub const COLUMN_NAME: &str = "stock";
+}
+
+/// Price of one item, in the smallest currency unit (e.g. cents)
+#[must_use]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct UnitPrice(pub i64);
+
+impl UnitPrice {
+    pub const COLUMN_NAME: &str = "unit_price";
+}
+
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::FromRow)]
+pub struct CatalogItem {
+    pub name: ItemName,
+    pub stock: Stock,
+    pub unit_price: UnitPrice,
+}
+
+impl CatalogItem {
+    pub const TABLE_NAME: &str = "catalog_items";
+}
+
+/// Contents of a catalog TOML file
+///
+/// ```toml
+/// [[items]]
+/// name = "Toy car"
+/// stock = 20
+/// unit_price = 1250
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct CatalogFile {
+    #[serde(default)]
+    pub items: Vec<CatalogItem>,
+}
+
+impl CatalogFile {
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use super::model::{CatalogItem, ItemName, Stock, UnitPrice};
+use super::Repository;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReserveError {
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error("Unknown item {0:?}")]
+    UnknownItem(ItemName),
+    #[error("Insufficient stock of {0:?}")]
+    InsufficientStock(ItemName),
+}
+
+impl Repository {
+    /// Insert items, or overwrite stock and price of existing ones
+    #[tracing::instrument(skip_all)]
+    pub async fn upsert_many(&self, items: &[CatalogItem]) -> sqlx::Result<()> {
+        let query = format!(
+            r#"
+                INSERT INTO "{table}" ("{name}", "{stock}", "{unit_price}") VALUES ($1, $2, $3)
+                ON CONFLICT ("{name}") DO UPDATE
+                SET "{stock}" = EXCLUDED."{stock}", "{unit_price}" = EXCLUDED."{unit_price}"
+            "#,
+            table = CatalogItem::TABLE_NAME,
+            name = ItemName::COLUMN_NAME,
+            stock = Stock::COLUMN_NAME,
+            unit_price = UnitPrice::COLUMN_NAME,
+        );
+        let mut tx = self.inner.pool.begin().await?;
+        for item in items {
+            sqlx::query(&query)
+                .bind(&item.name)
+                .bind(item.stock)
+                .bind(item.unit_price)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        tracing::info!(items = items.len(), "UPSERTed catalog items");
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn find_many(
+        &self,
+        names: &[ItemName],
+    ) -> sqlx::Result<HashMap<ItemName, CatalogItem>> {
+        let query = format!(
+            r#"SELECT * FROM "{}" WHERE "{}" = ANY($1)"#,
+            CatalogItem::TABLE_NAME,
+            ItemName::COLUMN_NAME,
+        );
+        let names: Vec<&str> = names.iter().map(|n| n.0.as_str()).collect();
+        let items: Vec<CatalogItem> = sqlx::query_as(&query)
+            .bind(names)
+            .fetch_all(&self.inner.pool)
+            .await?;
+        let items = items.into_iter().map(|i| (i.name.clone(), i)).collect();
+        Ok(items)
+    }
+
+    /// Take `quantity` of each item out of the stock, or nothing at all
+    #[tracing::instrument(skip_all)]
+    pub async fn reserve(&self, requests: &[(ItemName, Stock)]) -> Result<(), ReserveError> {
+        let query = format!(
+            r#"
+                UPDATE "{table}" SET "{stock}" = "{stock}" - $2
+                WHERE "{name}" = $1 AND "{stock}" >= $2
+                RETURNING *
+            "#,
+            table = CatalogItem::TABLE_NAME,
+            name = ItemName::COLUMN_NAME,
+            stock = Stock::COLUMN_NAME,
+        );
+        let exists_query = format!(
+            r#"SELECT * FROM "{}" WHERE "{}" = $1"#,
+            CatalogItem::TABLE_NAME,
+            ItemName::COLUMN_NAME,
+        );
+        let mut tx = self.inner.pool.begin().await?;
+        for (name, quantity) in requests {
+            let updated: Option<CatalogItem> = sqlx::query_as(&query)
+                .bind(name)
+                .bind(quantity)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if updated.is_some() {
+                continue;
+            }
+            let existing: Option<CatalogItem> = sqlx::query_as(&exists_query)
+                .bind(name)
+                .fetch_optional(&mut *tx)
+                .await?;
+            tx.rollback().await?;
+            return match existing {
+                Some(_) => Err(ReserveError::InsufficientStock(name.clone())),
+                None => Err(ReserveError::UnknownItem(name.clone())),
+            };
+        }
+        tx.commit().await?;
+        tracing::info!(items = requests.len(), "Reserved stock");
+        Ok(())
+    }
+
+    /// Put `quantity` of each item back into the stock, undoing [`Self::reserve`]
+    #[tracing::instrument(skip_all)]
+    pub async fn release(&self, requests: &[(ItemName, Stock)]) -> sqlx::Result<()> {
+        let query = format!(
+            r#"UPDATE "{table}" SET "{stock}" = "{stock}" + $2 WHERE "{name}" = $1"#,
+            table = CatalogItem::TABLE_NAME,
+            name = ItemName::COLUMN_NAME,
+            stock = Stock::COLUMN_NAME,
+        );
+        let mut tx = self.inner.pool.begin().await?;
+        for (name, quantity) in requests {
+            sqlx::query(&query)
+                .bind(name)
+                .bind(quantity)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        tracing::info!(items = requests.len(), "Released stock");
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
 mod game;
+pub mod games;
 mod model;
 
-pub use game::{Error as GameError, Team};
+use std::sync::Arc;
+
+pub use game::{Error as GameError, InvalidSize, Size, Status as GameStatus, Team};
+pub use games::GameId;
 pub use model::{Column, Grid, Tile};
 use rand::rngs::StdRng;
 
@@ -9,4 +13,14 @@ use rand::rngs::StdRng;
 pub struct Game {
     grid: Grid,
     rng: StdRng,
+    size: Size,
+    /// first team in strict mode
+    first: Option<Team>,
+    next: Option<Team>,
+}
+
+/// [`Game`]s addressed by [`GameId`]
+#[derive(Clone)]
+pub struct Games {
+    inner: Arc<games::Inner>,
 }
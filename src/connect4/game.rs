@@ -21,6 +21,15 @@ impl From<Team> for Tile {
     }
 }
 
+impl Team {
+    pub fn other(self) -> Self {
+        match self {
+            Self::Cookie => Self::Milk,
+            Self::Milk => Self::Cookie,
+        }
+    }
+}
+
 impl fmt::Display for Team {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let tile = Tile::from(*self);
@@ -36,39 +45,78 @@ pub enum Status {
     Wins(Team),
 }
 
-impl Status {
-    fn collect_lines(lines: impl IntoIterator<Item = LineStatus>) -> Self {
-        lines.into_iter().fold(Self::NoWinner, |s, l| match (s, l) {
-            (s @ Self::Wins(_), _) => s,
-            (_, LineStatus::OnlyCookie) => Self::Wins(Team::Cookie),
-            (_, LineStatus::OnlyMilk) => Self::Wins(Team::Milk),
-            (_, LineStatus::NotFilled) => Self::Playing,
-            (s, _) => s,
-        })
-    }
+// MARK: Size
+
+/// Board size and how many tiles in a row win
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct Size {
+    width: usize,
+    height: usize,
+    win_length: usize,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
-enum LineStatus {
-    #[default]
-    Initial,
-    OnlyCookie,
-    OnlyMilk,
-    NotFilled,
-    Mixed,
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum InvalidSize {
+    #[error("Board sides must be between 1 and {max}, got {width}x{height}", max = Size::MAX_SIDE)]
+    Side { width: usize, height: usize },
+    #[error("Win length must be between 1 and the longer side {side}, got {win_length}")]
+    WinLength { win_length: usize, side: usize },
 }
 
-impl LineStatus {
-    fn accept_tile(self, tile: Tile) -> Self {
-        match (self, tile) {
-            (_, Tile::Empty) => Self::NotFilled,
-            (Self::Initial | Self::OnlyCookie, Tile::Cookie) => Self::OnlyCookie,
-            (Self::Initial | Self::OnlyMilk, Tile::Milk) => Self::OnlyMilk,
-            _ => Self::Mixed,
+impl Size {
+    pub const MAX_SIDE: usize = 64;
+
+    /// the original 4x4 board won by full lines
+    pub const DEFAULT: Self = Self {
+        width: 4,
+        height: 4,
+        win_length: 4,
+    };
+
+    /// classic connect four
+    pub const CLASSIC: Self = Self {
+        width: 7,
+        height: 6,
+        win_length: 4,
+    };
+
+    pub fn new(width: usize, height: usize, win_length: usize) -> Result<Self, InvalidSize> {
+        let sides = 1..=Self::MAX_SIDE;
+        if !sides.contains(&width) || !sides.contains(&height) {
+            return Err(InvalidSize::Side { width, height });
+        }
+        let side = width.max(height);
+        if !(1..=side).contains(&win_length) {
+            return Err(InvalidSize::WinLength { win_length, side });
         }
+        Ok(Self {
+            width,
+            height,
+            win_length,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.win_length
     }
 }
 
+impl Default for Size {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+// MARK: Game
+
 #[derive(Debug, Clone, Copy, thiserror::Error)]
 pub enum Error {
     #[error("Invalid column {0}")]
@@ -77,6 +125,8 @@ pub enum Error {
     ColumnFulfilled(usize),
     #[error("Game already finished: {0:?}")]
     GameFinished(Status),
+    #[error("Not {team}'s turn, {next} plays next")]
+    OutOfTurn { team: Team, next: Team },
 }
 
 impl Default for Game {
@@ -90,43 +140,102 @@ impl fmt::Display for Game {
         for row in self.grid.rows() {
             writeln!(f, "⬜{row}⬜")?;
         }
-        writeln!(f, "⬜⬜⬜⬜⬜⬜")
+        writeln!(f, "{}", "⬜".repeat(self.size.width + 2))
     }
 }
 
 impl Game {
     const RNG_SEED: u64 = 2024;
 
+    /// (column, row) steps of the lines checked for a win, from the top left
+    const DIRECTIONS: [(usize, isize); 4] = [(1, 1), (1, -1), (1, 0), (0, 1)];
+
     pub fn new() -> Self {
-        let grid = Grid(vec![Column::default(); 4]);
+        Self::with_size(Size::DEFAULT)
+    }
+
+    pub fn with_size(size: Size) -> Self {
+        let grid = Grid::empty(size.width, size.height);
         let rng = StdRng::seed_from_u64(Self::RNG_SEED);
-        Self { grid, rng }
+        Self {
+            grid,
+            rng,
+            size,
+            first: None,
+            next: None,
+        }
+    }
+
+    /// Strict mode, where teams take turns starting with `first`
+    pub fn with_turns(self, first: Team) -> Self {
+        Self {
+            first: Some(first),
+            next: Some(first),
+            ..self
+        }
+    }
+
+    /// The team to play next in strict mode
+    pub fn next(&self) -> Option<Team> {
+        self.next
+    }
+
+    /// The team playing first after a reset in strict mode
+    pub fn first(&self) -> Option<Team> {
+        self.first
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
     }
 
     pub fn reset(&mut self) {
         self.rng = StdRng::seed_from_u64(Self::RNG_SEED);
-        self.grid.0.fill(Column::default());
+        self.grid.0.fill(Column::empty(self.size.height));
+        self.next = self.first;
     }
 
-    pub fn status(&self) -> Status {
-        let mut rows = [LineStatus::Initial; 4];
-        let mut cols = [LineStatus::Initial; 4];
-        // (left-top to right-bottom, left-bottom to right-top)
-        let mut diagonal = (LineStatus::Initial, LineStatus::Initial);
-        for (c, col) in self.grid.cols().map(|c| c.into_inner()).enumerate() {
-            let cs = &mut cols[c];
-            for (rs, t) in rows.iter_mut().zip(col) {
-                *rs = rs.accept_tile(t);
-                *cs = cs.accept_tile(t);
-            }
+    /// [`Self::reset`] to strict mode, where teams take turns starting with `first`
+    pub fn reset_with_turns(&mut self, first: Team) {
+        self.first = Some(first);
+        self.reset();
+    }
 
-            let (d1, d2) = &mut diagonal;
-            *d1 = d1.accept_tile(col[c]);
-            *d2 = d2.accept_tile(col[3 - c]);
+    /// The team of a run of [`Size::win_length`] tiles starting at `(col, row)`
+    fn run_from(&self, (col, row): (usize, usize), (dc, dr): (usize, isize)) -> Option<Team> {
+        let team = match self.grid.tile(col, row)? {
+            Tile::Empty => return None,
+            Tile::Cookie => Team::Cookie,
+            Tile::Milk => Team::Milk,
+        };
+        for i in 1..self.size.win_length {
+            let c = col + dc * i;
+            let r = row.checked_add_signed(dr * i as isize)?;
+            if self.grid.tile(c, r)? != Tile::from(team) {
+                return None;
+            }
         }
+        Some(team)
+    }
 
-        let lines = [diagonal.0, diagonal.1].into_iter().chain(rows).chain(cols);
-        Status::collect_lines(lines)
+    pub fn status(&self) -> Status {
+        let Size { width, height, .. } = self.size;
+        for direction in Self::DIRECTIONS {
+            let mut starts = (0..height).flat_map(|r| (0..width).map(move |c| (c, r)));
+            if let Some(team) = starts.find_map(|start| self.run_from(start, direction)) {
+                return Status::Wins(team);
+            }
+        }
+        let filled = self
+            .grid
+            .cols()
+            .flat_map(|c| c.as_inner())
+            .all(|t| *t != Tile::Empty);
+        if filled {
+            Status::NoWinner
+        } else {
+            Status::Playing
+        }
     }
 
     pub fn display_with_status(&self) -> DisplayWithStatus<'_> {
@@ -137,11 +246,13 @@ impl Game {
         if let s @ (Status::Wins(_) | Status::NoWinner) = self.status() {
             return Err(Error::GameFinished(s));
         }
-        let column = self
-            .grid
-            .as_inner_mut()
-            .get_mut(col)
-            .ok_or(Error::InvalidColumn(col))?;
+        if col >= self.size.width {
+            return Err(Error::InvalidColumn(col));
+        }
+        if let Some(next) = self.next.filter(|n| *n != team) {
+            return Err(Error::OutOfTurn { team, next });
+        }
+        let column = &mut self.grid.as_inner_mut()[col];
         let tile = column
             .as_inner_mut()
             .iter_mut()
@@ -149,11 +260,13 @@ impl Game {
             .find(|t| **t == Tile::Empty)
             .ok_or(Error::ColumnFulfilled(col))?;
         *tile = team.into();
+        self.next = self.next.map(Team::other);
         Ok(())
     }
 
     pub fn random_board(&mut self) {
-        let it = (0usize..4).flat_map(|r| (0usize..4).map(move |c| (r, c)));
+        let Size { width, height, .. } = self.size;
+        let it = (0..height).flat_map(|r| (0..width).map(move |c| (r, c)));
         for (r, c) in it {
             let tile = &mut self.grid.as_inner_mut()[c].as_inner_mut()[r];
             *tile = if self.rng.gen::<bool>() {
@@ -162,6 +275,11 @@ impl Game {
                 Tile::Milk
             };
         }
+        // as if the tiles were piled in turns
+        let piled = width * height;
+        self.next = self
+            .first
+            .map(|first| if piled % 2 == 0 { first } else { first.other() });
     }
 }
 
@@ -171,7 +289,10 @@ impl fmt::Display for DisplayWithStatus<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(self.0, f)?;
         match self.0.status() {
-            Status::Playing => Ok(()),
+            Status::Playing => match self.0.next {
+                Some(next) => writeln!(f, "Next: {next}"),
+                None => Ok(()),
+            },
             Status::Wins(team) => writeln!(f, "{team} wins!"),
             Status::NoWinner => writeln!(f, "No winner."),
         }
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use super::{Game, GameStatus, Size, Team};
+
+// MARK: id
+
+/// Id of a game in [`Games`](super::Games), `default` for the one behind the legacy routes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GameId(Uuid);
+
+impl GameId {
+    pub const DEFAULT: Self = Self(Uuid::nil());
+
+    fn new_v4() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::DEFAULT
+    }
+}
+
+impl Default for GameId {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_default() {
+            f.write_str("default")
+        } else {
+            fmt::Display::fmt(&self.0, f)
+        }
+    }
+}
+
+impl FromStr for GameId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::DEFAULT),
+            _ => Uuid::from_str(s).map(Self),
+        }
+    }
+}
+
+impl Serialize for GameId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// MARK: Inner
+
+struct Entry {
+    game: Arc<Mutex<Game>>,
+    last_seen: Instant,
+}
+
+pub(super) struct Inner {
+    idle_timeout: Duration,
+    max_games: usize,
+    /// never evicted nor deleted
+    default: Arc<Mutex<Game>>,
+    games: Mutex<HashMap<GameId, Entry>>,
+}
+
+// MARK: Builder
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Builder {
+    idle_timeout: Duration,
+    max_games: usize,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(3600),
+            max_games: 1000,
+        }
+    }
+}
+
+impl super::Games {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+impl Builder {
+    /// games untouched for this long are evicted
+    pub fn idle_timeout(self, value: Duration) -> Self {
+        Self {
+            idle_timeout: value,
+            ..self
+        }
+    }
+
+    /// games kept at most besides the default one, more are not created
+    pub fn max_games(self, value: usize) -> Self {
+        Self {
+            max_games: value,
+            ..self
+        }
+    }
+
+    pub fn build(self) -> super::Games {
+        let Self {
+            idle_timeout,
+            max_games,
+        } = self;
+        let inner = Inner {
+            idle_timeout,
+            max_games,
+            default: Arc::new(Mutex::new(Game::new())),
+            games: Mutex::new(HashMap::new()),
+        };
+        super::Games {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+impl Default for super::Games {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+// MARK: ops
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameSummary {
+    id: GameId,
+    #[serde(flatten)]
+    size: Size,
+    status: GameStatus,
+    /// only in strict mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<Team>,
+    idle_secs: f32,
+}
+
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("Too many games, at most {0} are kept")]
+pub struct TooManyGames(usize);
+
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum DeleteError {
+    #[error("The default game cannot be deleted")]
+    Default,
+    #[error("Game {0} not found")]
+    NotFound(GameId),
+}
+
+impl super::Games {
+    /// Fails while [`Builder::max_games`] games are kept
+    #[tracing::instrument(skip_all)]
+    pub async fn create(&self, game: Game) -> Result<GameId, TooManyGames> {
+        let mut games = self.inner.games.lock().await;
+        if games.len() >= self.inner.max_games {
+            tracing::warn!(max = self.inner.max_games, "too many games");
+            return Err(TooManyGames(self.inner.max_games));
+        }
+        let id = GameId::new_v4();
+        let entry = Entry {
+            game: Arc::new(Mutex::new(game)),
+            last_seen: Instant::now(),
+        };
+        games.insert(id, entry);
+        tracing::info!(%id, "created a game");
+        Ok(id)
+    }
+
+    /// The game, keeping it from being evicted for a while
+    pub async fn get(&self, id: GameId) -> Option<Arc<Mutex<Game>>> {
+        if id.is_default() {
+            return Some(Arc::clone(&self.inner.default));
+        }
+        let mut games = self.inner.games.lock().await;
+        let entry = games.get_mut(&id)?;
+        entry.last_seen = Instant::now();
+        Some(Arc::clone(&entry.game))
+    }
+
+    /// Every game, the default one first
+    pub async fn list(&self) -> Vec<GameSummary> {
+        let default = {
+            let game = self.inner.default.lock().await;
+            GameSummary {
+                id: GameId::DEFAULT,
+                size: game.size(),
+                status: game.status(),
+                next: game.next(),
+                idle_secs: 0.0,
+            }
+        };
+        // without holding the map while waiting for the games
+        let entries: Vec<_> = {
+            let games = self.inner.games.lock().await;
+            games
+                .iter()
+                .map(|(id, entry)| (*id, Arc::clone(&entry.game), entry.last_seen))
+                .collect()
+        };
+        let mut summaries = Vec::with_capacity(entries.len() + 1);
+        for (id, game, last_seen) in entries {
+            let game = game.lock().await;
+            summaries.push(GameSummary {
+                id,
+                size: game.size(),
+                status: game.status(),
+                next: game.next(),
+                idle_secs: last_seen.elapsed().as_secs_f32(),
+            });
+        }
+        summaries.sort_by(|l, r| l.id.cmp(&r.id));
+        summaries.insert(0, default);
+        summaries
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn delete(&self, id: GameId) -> Result<(), DeleteError> {
+        if id.is_default() {
+            return Err(DeleteError::Default);
+        }
+        let mut games = self.inner.games.lock().await;
+        games.remove(&id).ok_or(DeleteError::NotFound(id))?;
+        tracing::info!("deleted a game");
+        Ok(())
+    }
+
+    /// Drop games untouched for the idle timeout, returning how many were dropped
+    pub async fn evict_idle(&self) -> usize {
+        let mut games = self.inner.games.lock().await;
+        let before = games.len();
+        games.retain(|_, entry| entry.last_seen.elapsed() < self.inner.idle_timeout);
+        before - games.len()
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn evict_task(self) {
+        let mut interval = tokio::time::interval(self.inner.idle_timeout);
+        interval.tick().await; // ignore immediate tick
+        loop {
+            interval.tick().await;
+            let evicted = self.evict_idle().await;
+            if evicted > 0 {
+                tracing::info!(evicted, "evicted idle games");
+            }
+        }
+    }
+}
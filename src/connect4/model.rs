@@ -121,12 +121,20 @@ impl fmt::Display for Tile {
 }
 
 newtype! {
-    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+    /// Tiles from the top to the bottom
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
     #[serde(transparent)]
-    pub struct Column(pub(super) [Tile; 4]);
+    pub struct Column(pub(super) Vec<Tile>);
+}
+
+impl Column {
+    pub(super) fn empty(height: usize) -> Self {
+        Self(vec![Tile::Empty; height])
+    }
 }
 
 newtype! {
+    /// Columns from the left to the right
     #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
     #[serde(transparent)]
     pub struct Grid(pub(super) Vec<Column>);
@@ -147,7 +155,7 @@ impl Row<'_> {
 
 impl fmt::Display for Row<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..4 {
+        for i in 0..self.grid.width() {
             write!(f, "{}", self.tile_at(i).unwrap())?;
         }
         Ok(())
@@ -155,8 +163,25 @@ impl fmt::Display for Row<'_> {
 }
 
 impl Grid {
+    pub(super) fn empty(width: usize, height: usize) -> Self {
+        Self(vec![Column::empty(height); width])
+    }
+
+    pub(super) fn width(&self) -> usize {
+        self.0.len()
+    }
+
+    pub(super) fn height(&self) -> usize {
+        self.0.first().map_or(0, |c| c.0.len())
+    }
+
+    /// `None` out of the grid
+    pub(super) fn tile(&self, col: usize, row: usize) -> Option<Tile> {
+        self.0.get(col)?.0.get(row).copied()
+    }
+
     pub(super) fn row_at(&self, at: usize) -> Option<Row<'_>> {
-        if at <= 3 {
+        if at < self.height() {
             Some(Row { grid: self, at })
         } else {
             None
@@ -164,7 +189,7 @@ impl Grid {
     }
 
     pub(super) fn rows(&self) -> impl Iterator<Item = Row<'_>> + '_ {
-        (0..4).flat_map(|i| self.row_at(i))
+        (0..self.height()).flat_map(|i| self.row_at(i))
     }
 
     pub(super) fn col_at(&self, at: usize) -> Option<&Column> {
@@ -172,6 +197,6 @@ impl Grid {
     }
 
     pub(super) fn cols(&self) -> impl Iterator<Item = &'_ Column> {
-        (0..4).flat_map(|i| self.col_at(i))
+        (0..self.width()).flat_map(|i| self.col_at(i))
     }
 }
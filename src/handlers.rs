@@ -5,7 +5,9 @@ use std::sync::Arc;
 
 use warp::{http, hyper};
 
-use crate::bucket::Liters;
+use crate::bucket::clients::ClientKey;
+use crate::bucket::{self, Liters};
+use crate::connect4::GameId;
 
 // MARK: mod
 
@@ -15,6 +17,7 @@ pub(crate) mod ipv4_dest;
 pub(crate) mod ipv4_key;
 pub(crate) mod ipv6_dest;
 pub(crate) mod ipv6_key;
+pub(crate) mod lockfile;
 pub(crate) mod manifest;
 pub(crate) mod milk;
 pub(crate) mod quotes;
@@ -101,50 +104,397 @@ pub async fn ipv6_key(query: ipv6_key::Query) -> Result<Response, Infallible> {
 pub async fn manifest_order(
     state: Arc<manifest::State>,
     manifest: manifest::Manifest,
+    query: manifest::OrderQuery,
 ) -> Result<Response, Infallible> {
-    use manifest::ProperOrder;
+    use crate::catalog::ops::ReserveError;
+    use manifest::{OutputFormat, ProperOrder};
 
-    if !manifest::manifest_key_included(&state, &manifest) {
-        let body = "Magic keyword not provided".to_string();
+    if let Err(e) = manifest::check_keywords(&state, &manifest) {
+        tracing::info!(
+            err = &e as &dyn std::error::Error,
+            "keyword policy violated"
+        );
         let res = Response::builder()
             .status(http::StatusCode::BAD_REQUEST)
-            .body(hyper::Body::from(body))
+            .body(hyper::Body::from(format!(
+                "Magic keyword not provided: {e}"
+            )))
             .unwrap();
         return Ok(res);
     }
     tracing::info!(?manifest);
-    let orders = manifest
+    let extracted = manifest
         .package
         .as_ref()
         .and_then(|p| p.metadata.as_ref())
         .and_then(ProperOrder::from_value)
         .unwrap_or_default();
+    let orders = match query.apply(extracted.clone()) {
+        Ok(o) => o,
+        Err(e) => {
+            tracing::info!(err = &e as &dyn std::error::Error, "aggregation failed");
+            let res = Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e.to_string()))
+                .unwrap();
+            return Ok(res);
+        }
+    };
+    let confirm = manifest::confirm_requested(&manifest);
+    // plain orders are answered without looking up the catalog
+    let mut invoice = if confirm || query.format == OutputFormat::Json {
+        match manifest::Invoice::of(&state, &orders).await {
+            Ok(i) => i,
+            Err(e) => {
+                tracing::error!(
+                    err = &e as &dyn std::error::Error,
+                    "Failed to look up the catalog"
+                );
+                let res = Response::builder()
+                    .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(hyper::Body::empty())
+                    .unwrap();
+                return Ok(res);
+            }
+        }
+    } else {
+        manifest::Invoice::default()
+    };
+    if confirm {
+        let reserved = if invoice.is_available() {
+            invoice.reserve(&state, &orders).await
+        } else {
+            Ok(())
+        };
+        match reserved {
+            Ok(()) if invoice.is_available() => {}
+            Ok(()) | Err(ReserveError::UnknownItem(_) | ReserveError::InsufficientStock(_)) => {
+                tracing::info!("Could not reserve stock for orders");
+                let res = if query.format == OutputFormat::Json {
+                    let body = manifest::OrdersResponse::new(orders, invoice);
+                    Response::builder()
+                        .status(http::StatusCode::CONFLICT)
+                        .header(http::header::CONTENT_TYPE, "application/json")
+                        .body(hyper::Body::from(serde_json::to_string(&body).unwrap()))
+                        .unwrap()
+                } else {
+                    let body = invoice
+                        .unavailable_lines()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let body = if body.is_empty() {
+                        "Stock changed while reserving".to_string()
+                    } else {
+                        body
+                    };
+                    Response::builder()
+                        .status(http::StatusCode::CONFLICT)
+                        .body(hyper::Body::from(body))
+                        .unwrap()
+                };
+                return Ok(res);
+            }
+            Err(ReserveError::Database(e)) => {
+                tracing::error!(
+                    err = &e as &dyn std::error::Error,
+                    "Failed to reserve stock"
+                );
+                let res = Response::builder()
+                    .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(hyper::Body::empty())
+                    .unwrap();
+                return Ok(res);
+            }
+        }
+    }
+    let submission = manifest::submission_of(&manifest, extracted);
+    if let Some(submission) = submission {
+        if let Err(e) = state.submissions.create(submission).await {
+            tracing::error!(
+                err = &e as &dyn std::error::Error,
+                "Failed to store a submission"
+            );
+            if let Err(e) = invoice.release(&state, &orders).await {
+                tracing::error!(
+                    err = &e as &dyn std::error::Error,
+                    "Failed to release reserved stock"
+                );
+            }
+            let res = Response::builder()
+                .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                .body(hyper::Body::empty())
+                .unwrap();
+            return Ok(res);
+        }
+    }
+    if orders.is_empty() && query.format == OutputFormat::Text {
+        let res = Response::builder()
+            .status(http::StatusCode::NO_CONTENT)
+            .body(hyper::Body::empty())
+            .unwrap();
+        return Ok(res);
+    }
+    if query.format == OutputFormat::Json {
+        let body = manifest::OrdersResponse::new(orders, invoice);
+        let body = serde_json::to_string(&body).unwrap();
+        let res = Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(body))
+            .unwrap();
+        return Ok(res);
+    }
     let orders = orders
         .iter()
         .map(ProperOrder::to_string)
         .collect::<Vec<_>>()
         .join("\n");
-    let status = if orders.is_empty() {
-        http::StatusCode::NO_CONTENT
-    } else {
+    let body = hyper::Body::from(orders);
+    let res = Response::builder()
+        .status(http::StatusCode::OK)
+        .body(body)
+        .unwrap();
+    Ok(res)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn manifest_report(
+    state: Arc<manifest::State>,
+    content_type: String,
+    body: bytes::Bytes,
+) -> Result<Response, Infallible> {
+    let report = manifest::Report::build(&state, &content_type, &body);
+    Ok(manifest_report_response(report))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn manifest_workspace_report(
+    state: Arc<manifest::State>,
+    member: Option<bytes::Bytes>,
+    root: Option<bytes::Bytes>,
+) -> Result<Response, Infallible> {
+    let report = manifest::Report::build_workspace(&state, member.as_deref(), root.as_deref());
+    Ok(manifest_report_response(report))
+}
+
+fn manifest_report_response(report: manifest::Report) -> Response {
+    let status = if report.is_valid() {
         http::StatusCode::OK
+    } else {
+        tracing::info!(?report, "invalid manifest");
+        http::StatusCode::BAD_REQUEST
+    };
+    let body = serde_json::to_string(&report).unwrap();
+    Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap()
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn submissions_list(
+    state: Arc<manifest::State>,
+    query: manifest::SubmissionListQuery,
+) -> Result<Response, Infallible> {
+    let page = match query.page() {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::info!(err = &e as &dyn std::error::Error, "bad request");
+            let res = Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e.to_string()))
+                .unwrap();
+            return Ok(res);
+        }
+    };
+    let res = match state.submissions.list(page, query.per_page()).await {
+        Ok(list) => {
+            tracing::info!("Listed submissions");
+            serde_json::to_string(&list).map_err(|e| {
+                tracing::error!(
+                    err = &e as &dyn std::error::Error,
+                    "Failed to serialize response"
+                );
+                http::StatusCode::INTERNAL_SERVER_ERROR
+            })
+        }
+        Err(e) => {
+            tracing::error!(
+                err = &e as &dyn std::error::Error,
+                "Failed to list submissions"
+            );
+            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+    let res = match res {
+        Ok(body) => Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(body))
+            .unwrap(),
+        Err(status) => Response::builder()
+            .status(status)
+            .body(hyper::Body::empty())
+            .unwrap(),
+    };
+    Ok(res)
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn submissions_get(
+    state: Arc<manifest::State>,
+    param: manifest::SubmissionPathParam,
+) -> Result<Response, Infallible> {
+    let manifest::SubmissionPathParam { id } = param;
+    let res = match state.submissions.find_one(id).await {
+        Ok(Some(submission)) => {
+            tracing::info!("Found one submission");
+            serde_json::to_string(&submission).map_err(|e| {
+                tracing::error!(
+                    err = &e as &dyn std::error::Error,
+                    ?submission,
+                    "Failed to serialize submission"
+                );
+                http::StatusCode::INTERNAL_SERVER_ERROR
+            })
+        }
+        Ok(None) => {
+            tracing::info!("No matching submission found");
+            Err(http::StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!(
+                err = &e as &dyn std::error::Error,
+                "Failed to find a submission"
+            );
+            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+    let res = match res {
+        Ok(body) => Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(body))
+            .unwrap(),
+        Err(status) => Response::builder()
+            .status(status)
+            .body(hyper::Body::empty())
+            .unwrap(),
+    };
+    Ok(res)
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn submissions_totals(
+    state: Arc<manifest::State>,
+    query: manifest::TotalsQuery,
+) -> Result<Response, Infallible> {
+    let res = match state.submissions.totals(query).await {
+        Ok(totals) => {
+            tracing::info!("Aggregated totals per item");
+            serde_json::to_string(&totals).map_err(|e| {
+                tracing::error!(
+                    err = &e as &dyn std::error::Error,
+                    "Failed to serialize totals"
+                );
+                http::StatusCode::INTERNAL_SERVER_ERROR
+            })
+        }
+        Err(e) => {
+            tracing::error!(
+                err = &e as &dyn std::error::Error,
+                "Failed to aggregate totals"
+            );
+            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+    let res = match res {
+        Ok(body) => Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(body))
+            .unwrap(),
+        Err(status) => Response::builder()
+            .status(status)
+            .body(hyper::Body::empty())
+            .unwrap(),
     };
-    let body = hyper::Body::from(orders);
-    let res = Response::builder().status(status).body(body).unwrap();
+    Ok(res)
+}
+
+pub async fn manifest_diff(
+    old: manifest::Manifest,
+    new: manifest::Manifest,
+) -> Result<Response, Infallible> {
+    let diff = match manifest::ManifestDiff::between(&old, &new) {
+        Ok(d) => d,
+        Err(e) => {
+            tracing::error!(
+                err = &e as &dyn std::error::Error,
+                "Failed to tabulate the package"
+            );
+            let res = Response::builder()
+                .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                .body(hyper::Body::empty())
+                .unwrap();
+            return Ok(res);
+        }
+    };
+    tracing::info!(?diff);
+    let body = serde_json::to_string(&diff).unwrap();
+    let res = Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap();
+    Ok(res)
+}
+
+// MARK: lockfile
+
+#[tracing::instrument(skip(lockfile))]
+pub async fn lockfile_report(
+    lockfile: lockfile::Lockfile,
+    query: lockfile::ReportQuery,
+) -> Result<Response, Infallible> {
+    use lockfile::ReportFormat;
+
+    let (content_type, body) = match query.format {
+        ReportFormat::Json => {
+            let report = lockfile::Report::of(&lockfile);
+            tracing::info!(?report);
+            ("application/json", serde_json::to_string(&report).unwrap())
+        }
+        ReportFormat::Html => ("text/html; charset=utf-8", lockfile::swatches(&lockfile)),
+    };
+    let res = Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, content_type)
+        .body(hyper::Body::from(body))
+        .unwrap();
     Ok(res)
 }
 
 // MARK: milk factory
 
-pub async fn request_milk(state: Arc<milk::State>) -> Result<Response, Infallible> {
-    let flow = milk::check_bucket(Arc::clone(&state)).await;
-    let _ = state.bucket.withdraw_by(Liters(1.0)).await;
-    if let ControlFlow::Break(res) = flow {
-        tracing::error!("rate limit reached");
-        return Ok(res);
-    }
+pub async fn request_milk(
+    state: Arc<milk::State>,
+    client: ClientKey,
+    wait: milk::WaitRequest,
+) -> Result<Response, Infallible> {
+    let wait = match wait.duration() {
+        Ok(w) => w,
+        Err(e) => return Ok(milk::invalid_wait(&e)),
+    };
+    let limiter = state.limiter_for(client).await;
+    let pack = match milk::withdraw(&*limiter, Liters(1.0), wait).await {
+        Ok(p) => p,
+        Err(e) => return Ok(milk::empty_bucket(&e)),
+    };
     let body = hyper::Body::from("Milk withdrawn\n".to_string());
-    let res = Response::builder()
+    let res = milk::rate_limit_headers(Response::builder(), pack.quota())
         .status(http::StatusCode::OK)
         .header(http::header::CONTENT_TYPE, "plain/text")
         .body(body)
@@ -154,17 +504,40 @@ pub async fn request_milk(state: Arc<milk::State>) -> Result<Response, Infallibl
 
 pub async fn convert_milk_unit(
     state: Arc<milk::State>,
+    client: ClientKey,
+    wait: milk::WaitRequest,
+    query: milk::ConvertQuery,
     request: bytes::Bytes,
 ) -> Result<Response, milk::Error> {
-    let flow = milk::check_bucket(Arc::clone(&state)).await;
-    let _ = state.bucket.withdraw_by(Liters(1.0)).await;
-    if let ControlFlow::Break(res) = flow {
-        tracing::error!("rate limit reached");
-        return Ok(res);
+    let wait = match wait.duration() {
+        Ok(w) => w,
+        Err(e) => return Ok(milk::invalid_wait(&e)),
+    };
+    if let Some(withdraw) = milk::WithdrawRequest::detect(&request) {
+        return withdraw_milk(state, client, wait, withdraw?).await;
     }
-    let request = std::str::from_utf8(&request)?;
-    let request: milk::Unit = serde_json::from_str(request)?;
-    let response = request.convert();
+    let exact = match query.exact() {
+        Ok(e) => e,
+        Err(e) => return Ok(milk::invalid_exact(&e)),
+    };
+    let limiter = state.limiter_for(client).await;
+    let pack = match milk::withdraw(&*limiter, Liters(1.0), wait).await {
+        Ok(p) => p,
+        Err(e) => return Ok(milk::empty_bucket(&e)),
+    };
+    let response = match milk::ConvertRequest::parse(&request) {
+        // an unknown target unit is as invalid as an unknown source unit
+        Ok(request) => request
+            .convert(exact)
+            .map_err(<serde_json::Error as serde::de::Error>::custom)
+            .map_err(milk::Error::from),
+        Err(e) => Err(e),
+    };
+    let response = match response {
+        Ok(r) => r,
+        // the milk is gone already, so tell how much is left
+        Err(e) => return Ok(milk::invalid_conversion(&e, pack.quota())),
+    };
     let body = serde_json::to_string(&response);
     let (status, content_type, body) = match body {
         Ok(b) => (
@@ -174,7 +547,7 @@ pub async fn convert_milk_unit(
         ),
         Err(e) => {
             let err = &e as &dyn std::error::Error;
-            tracing::error!(err, "failed to serialize unit {request:?}");
+            tracing::error!(err, "failed to serialize unit {response:?}");
             (
                 http::StatusCode::INTERNAL_SERVER_ERROR,
                 "plain/text",
@@ -182,7 +555,7 @@ pub async fn convert_milk_unit(
             )
         }
     };
-    let res = Response::builder()
+    let res = milk::rate_limit_headers(Response::builder(), pack.quota())
         .status(status)
         .header(http::header::CONTENT_TYPE, content_type)
         .body(body)
@@ -190,19 +563,232 @@ pub async fn convert_milk_unit(
     Ok(res)
 }
 
-pub async fn refill_milk(state: Arc<milk::State>) -> Result<Response, Infallible> {
-    state.bucket.fulfill().await;
-    let res = Response::builder()
+/// [`milk::WithdrawRequest`] in any unit of the registry, delivered by its [`milk::FulfilmentPolicy`]
+async fn withdraw_milk(
+    state: Arc<milk::State>,
+    client: ClientKey,
+    wait: Option<std::time::Duration>,
+    request: milk::WithdrawRequest,
+) -> Result<Response, milk::Error> {
+    let liters = match request.liters() {
+        Ok(l) => l,
+        Err(e) => return Ok(milk::invalid_withdraw(&e)),
+    };
+    let limiter = state.limiter_for(client).await;
+    let pack = milk::withdraw_by_policy(&*limiter, liters, wait, request.policy()).await;
+    let pack = match pack {
+        Ok(p) => p,
+        Err(e) => return Ok(milk::empty_bucket(&e)),
+    };
+    tracing::info!(requested = ?liters, delivered = ?pack.inner(), "milk delivered");
+    let body = serde_json::to_string(&request.delivered(&pack))?;
+    let res = milk::rate_limit_headers(Response::builder(), pack.quota())
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap();
+    Ok(res)
+}
+
+pub async fn refill_milk(
+    state: Arc<milk::State>,
+    client: ClientKey,
+) -> Result<Response, Infallible> {
+    let limiter = state.limiter_for(client).await;
+    limiter.fulfill().await;
+    let res = milk::rate_limit_headers(Response::builder(), &limiter.quota().await)
         .status(http::StatusCode::OK)
         .body(hyper::Body::empty())
         .unwrap();
     Ok(res)
 }
 
+/// Level changes of the bucket of the client as server-sent events, for [`Algorithm::Bucket`](crate::bucket::limiter::Algorithm::Bucket)
+pub async fn milk_stream(
+    state: Arc<milk::State>,
+    client: ClientKey,
+    last_event_id: Option<u64>,
+) -> Result<Response, Infallible> {
+    use futures_util::StreamExt;
+    use warp::Reply;
+
+    let limiter = state.limiter_for(client).await;
+    let Some(bucket) = limiter.as_bucket() else {
+        let algorithm = limiter.algorithm();
+        tracing::info!(?algorithm, "level stream is not supported");
+        let res = Response::builder()
+            .status(http::StatusCode::NOT_IMPLEMENTED)
+            .header(http::header::CONTENT_TYPE, "plain/text")
+            .body(hyper::Body::from(
+                "Level stream is only available with the bucket algorithm\n",
+            ))
+            .unwrap();
+        return Ok(res);
+    };
+    let events = bucket
+        .clone()
+        .level_events(last_event_id)
+        .map(|e| Ok::<_, Infallible>(milk::sse_event(e)));
+    let keep_alive = warp::sse::keep_alive()
+        .interval(milk::STREAM_HEARTBEAT)
+        .text("heartbeat");
+    let res = warp::sse::reply(keep_alive.stream(events)).into_response();
+    Ok(res)
+}
+
+pub async fn milk_buckets(
+    state: Arc<milk::State>,
+    authorization: Option<String>,
+) -> Result<Response, Infallible> {
+    if let ControlFlow::Break(res) = milk::check_admin(&state, authorization.as_deref()) {
+        tracing::info!("unauthorized admin request");
+        return Ok(res);
+    }
+    let body = milk::BucketsResponse::collect(&state).await;
+    let body = serde_json::to_string(&body).unwrap();
+    let res = Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap();
+    Ok(res)
+}
+
+pub async fn milk_rate(state: Arc<milk::State>) -> Result<Response, Infallible> {
+    let body = milk::RateBody::from(state.refill_rate());
+    let body = serde_json::to_string(&body).unwrap();
+    let res = Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap();
+    Ok(res)
+}
+
+#[tracing::instrument(skip(state, authorization))]
+pub async fn set_milk_rate(
+    state: Arc<milk::State>,
+    authorization: Option<String>,
+    body: milk::RateBody,
+) -> Result<Response, Infallible> {
+    if let ControlFlow::Break(res) = milk::check_admin(&state, authorization.as_deref()) {
+        tracing::info!("unauthorized admin request");
+        return Ok(res);
+    }
+    let rate = match bucket::milk::RefillRate::try_from(body) {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::info!(err = %e, "invalid refill rate");
+            let res = Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .header(http::header::CONTENT_TYPE, "plain/text")
+                .body(hyper::Body::from(format!("{e}\n")))
+                .unwrap();
+            return Ok(res);
+        }
+    };
+    state.set_refill_rate(rate).await;
+    tracing::info!(?rate, "refill rate changed");
+    let body = serde_json::to_string(&milk::RateBody::from(rate)).unwrap();
+    let res = Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap();
+    Ok(res)
+}
+
 // MARK: connect4
 
-pub async fn connect4_board(state: Arc<connect4::State>) -> Result<Response, Infallible> {
-    let game = state.game.lock().await;
+fn connect4_game_not_found(id: GameId) -> Response {
+    tracing::info!(%id, "game not found");
+    Response::builder()
+        .status(http::StatusCode::NOT_FOUND)
+        .body(hyper::Body::empty())
+        .unwrap()
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn connect4_create(
+    state: Arc<connect4::State>,
+    body: bytes::Bytes,
+) -> Result<Response, Infallible> {
+    let game = connect4::CreateRequest::parse(&body)
+        .map_err(connect4::InvalidCreate::from)
+        .and_then(|r| r.game());
+    let game = match game {
+        Ok(g) => g,
+        Err(e) => {
+            tracing::info!(err = &e as &dyn std::error::Error, "bad request");
+            let res = Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e.to_string()))
+                .unwrap();
+            return Ok(res);
+        }
+    };
+    let (size, next) = (game.size(), game.next());
+    let id = match state.games.create(game).await {
+        Ok(id) => id,
+        Err(e) => {
+            let res = Response::builder()
+                .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                .body(hyper::Body::from(e.to_string()))
+                .unwrap();
+            return Ok(res);
+        }
+    };
+    let body = serde_json::to_string(&connect4::Created { id, size, next }).unwrap();
+    let res = Response::builder()
+        .status(http::StatusCode::CREATED)
+        .header(http::header::LOCATION, format!("/12/games/{id}"))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap();
+    Ok(res)
+}
+
+pub async fn connect4_list(state: Arc<connect4::State>) -> Result<Response, Infallible> {
+    let games = state.games.list().await;
+    let body = serde_json::to_string(&games).unwrap();
+    let res = Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(body))
+        .unwrap();
+    Ok(res)
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn connect4_delete(
+    state: Arc<connect4::State>,
+    id: GameId,
+) -> Result<Response, Infallible> {
+    use crate::connect4::games::DeleteError;
+
+    let (status, body) = match state.games.delete(id).await {
+        Ok(()) => (http::StatusCode::NO_CONTENT, hyper::Body::empty()),
+        Err(e) => {
+            tracing::info!(err = &e as &dyn std::error::Error, "deletion failed");
+            let status = match e {
+                DeleteError::Default => http::StatusCode::CONFLICT,
+                DeleteError::NotFound(_) => http::StatusCode::NOT_FOUND,
+            };
+            (status, hyper::Body::from(e.to_string()))
+        }
+    };
+    let res = Response::builder().status(status).body(body).unwrap();
+    Ok(res)
+}
+
+pub async fn connect4_board(
+    state: Arc<connect4::State>,
+    id: GameId,
+) -> Result<Response, Infallible> {
+    let Some(game) = state.games.get(id).await else {
+        return Ok(connect4_game_not_found(id));
+    };
+    let game = game.lock().await;
     let body = game.display_with_status().to_string();
     let res = Response::builder()
         .status(http::StatusCode::OK)
@@ -212,9 +798,34 @@ pub async fn connect4_board(state: Arc<connect4::State>) -> Result<Response, Inf
     Ok(res)
 }
 
-pub async fn connect4_reset(state: Arc<connect4::State>) -> Result<Response, Infallible> {
-    let mut game = state.game.lock().await;
-    game.reset();
+pub async fn connect4_reset(
+    state: Arc<connect4::State>,
+    id: GameId,
+    body: bytes::Bytes,
+) -> Result<Response, Infallible> {
+    let request = match connect4::ResetRequest::parse(&body) {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::info!(err = &e as &dyn std::error::Error, "bad request");
+            let res = Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e.to_string()))
+                .unwrap();
+            return Ok(res);
+        }
+    };
+    let Some(game) = state.games.get(id).await else {
+        return Ok(connect4_game_not_found(id));
+    };
+    let mut game = game.lock().await;
+    if let Err(e) = request.apply(&mut game) {
+        tracing::info!(err = &e as &dyn std::error::Error, "bad request");
+        let res = Response::builder()
+            .status(http::StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from(e.to_string()))
+            .unwrap();
+        return Ok(res);
+    }
     let body = game.display_with_status().to_string();
     let res = Response::builder()
         .status(http::StatusCode::OK)
@@ -227,16 +838,24 @@ pub async fn connect4_reset(state: Arc<connect4::State>) -> Result<Response, Inf
 #[tracing::instrument(skip(state))]
 pub async fn connect4_place(
     state: Arc<connect4::State>,
+    id: GameId,
     param: connect4::PlacePathParam,
 ) -> Result<Response, Infallible> {
     use crate::connect4::GameError;
 
     let connect4::PlacePathParam { team, col } = param;
-    let mut game = state.game.lock().await;
+    let Some(game) = state.games.get(id).await else {
+        return Ok(connect4_game_not_found(id));
+    };
+    let mut game = game.lock().await;
     let (status, body) = if let Err(err) = game.pile(team, col) {
         tracing::info!(err = &err as &dyn std::error::Error, "placement failed");
         match err {
             e @ GameError::InvalidColumn(_) => (http::StatusCode::BAD_REQUEST, e.to_string()),
+            GameError::OutOfTurn { .. } => (
+                http::StatusCode::CONFLICT,
+                game.display_with_status().to_string(),
+            ),
             _ => (
                 http::StatusCode::SERVICE_UNAVAILABLE,
                 game.display_with_status().to_string(),
@@ -254,8 +873,14 @@ pub async fn connect4_place(
     Ok(res)
 }
 
-pub async fn connect4_random_board(state: Arc<connect4::State>) -> Result<Response, Infallible> {
-    let mut game = state.game.lock().await;
+pub async fn connect4_random_board(
+    state: Arc<connect4::State>,
+    id: GameId,
+) -> Result<Response, Infallible> {
+    let Some(game) = state.games.get(id).await else {
+        return Ok(connect4_game_not_found(id));
+    };
+    let mut game = game.lock().await;
     game.random_board();
     let body = game.display_with_status().to_string();
     let res = Response::builder()
@@ -1,18 +1,21 @@
+use std::future::Future;
+
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
 
-use crate::connect4::{Game, Team};
+use crate::connect4::{Game, GameId, Games, InvalidSize, Size, Team};
 
+#[derive(Default)]
 pub struct State {
-    pub(super) game: Mutex<Game>,
+    pub(super) games: Games,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        let game = Game::default();
-        Self {
-            game: Mutex::new(game),
-        }
+impl State {
+    pub fn new(games: Games) -> Self {
+        Self { games }
+    }
+
+    pub fn evict_task(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.games.clone().evict_task()
     }
 }
 
@@ -27,3 +30,100 @@ impl PlacePathParam {
         Self { team, col }
     }
 }
+
+/// Body of `POST /12/games`, omitted fields are those of [`Size::DEFAULT`]
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct CreateRequest {
+    width: Option<usize>,
+    height: Option<usize>,
+    win_length: Option<usize>,
+    /// teams take turns, see [`Game::with_turns`]
+    #[serde(default)]
+    strict: bool,
+    /// first team in strict mode, [`Team::Cookie`] by default
+    first: Option<Team>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidCreate {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Size(#[from] InvalidSize),
+    #[error("A first team needs strict mode")]
+    FirstWithoutStrict,
+}
+
+impl CreateRequest {
+    /// An empty body creates a default game
+    pub fn parse(body: &[u8]) -> Result<Self, serde_json::Error> {
+        if body.trim_ascii().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_slice(body)
+    }
+
+    pub fn size(&self) -> Result<Size, InvalidSize> {
+        let default = Size::DEFAULT;
+        Size::new(
+            self.width.unwrap_or(default.width()),
+            self.height.unwrap_or(default.height()),
+            self.win_length.unwrap_or(default.win_length()),
+        )
+    }
+
+    pub fn game(&self) -> Result<Game, InvalidCreate> {
+        let game = Game::with_size(self.size()?);
+        if !self.strict {
+            return match self.first {
+                Some(_) => Err(InvalidCreate::FirstWithoutStrict),
+                None => Ok(game),
+            };
+        }
+        Ok(game.with_turns(self.first.unwrap_or(Team::Cookie)))
+    }
+}
+
+/// Body of `POST .../reset`, which may be empty
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResetRequest {
+    /// first team from now on, only in strict mode
+    first: Option<Team>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidReset {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("A first team needs strict mode")]
+    FirstWithoutStrict,
+}
+
+impl ResetRequest {
+    pub fn parse(body: &[u8]) -> Result<Self, serde_json::Error> {
+        if body.trim_ascii().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_slice(body)
+    }
+
+    /// Reset `game`, with the requested first team if any
+    pub fn apply(&self, game: &mut Game) -> Result<(), InvalidReset> {
+        match self.first {
+            None => game.reset(),
+            Some(_) if game.first().is_none() => return Err(InvalidReset::FirstWithoutStrict),
+            Some(first) => game.reset_with_turns(first),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Created {
+    pub(super) id: GameId,
+    #[serde(flatten)]
+    pub(super) size: Size,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) next: Option<Team>,
+}
@@ -0,0 +1,287 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+// MARK: model
+
+/// Contents of a `Cargo.lock`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Lockfile {
+    version: Option<u32>,
+    #[serde(default)]
+    package: Vec<Package>,
+    /// `"checksum {name} {version} ({source})" = "{checksum}"` of lockfile format v1
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Package {
+    name: String,
+    version: String,
+    source: Option<Source>,
+    checksum: Option<Checksum>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Source(String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    CratesIo,
+    Registry,
+    Git,
+    Path,
+    Other,
+}
+
+impl Source {
+    const CRATES_IO: [&str; 2] = [
+        "registry+https://github.com/rust-lang/crates.io-index",
+        "sparse+https://index.crates.io/",
+    ];
+
+    pub fn kind(&self) -> SourceKind {
+        if Self::CRATES_IO.contains(&self.0.as_str()) {
+            return SourceKind::CratesIo;
+        }
+        match self.0.split_once('+').map(|(protocol, _)| protocol) {
+            Some("registry" | "sparse") => SourceKind::Registry,
+            Some("git") => SourceKind::Git,
+            Some("path") => SourceKind::Path,
+            _ => SourceKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// SHA-256 digest of a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Checksum([u8; 32]);
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("invalid checksum {0:?}: expected 64 hex digits")]
+pub struct InvalidChecksum(String);
+
+impl TryFrom<String> for Checksum {
+    type Error = InvalidChecksum;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::str::FromStr for Checksum {
+    type Err = InvalidChecksum;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidChecksum(s.to_string());
+        if s.len() != 64 || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let mut digest = [0; 32];
+        for (byte, hex) in digest.iter_mut().zip(s.as_bytes().chunks(2)) {
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(hex, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(digest))
+    }
+}
+
+impl From<Checksum> for String {
+    fn from(value: Checksum) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+impl Checksum {
+    /// `#rrggbb` from the first 3 bytes
+    pub fn color(&self) -> String {
+        let [r, g, b, ..] = self.0;
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+impl Lockfile {
+    pub fn packages(&self) -> &[Package] {
+        &self.package
+    }
+
+    /// checksum of the package, looking into `[metadata]` for lockfile format v1
+    pub fn checksum_of(&self, package: &Package) -> Option<Checksum> {
+        if let Some(c) = package.checksum {
+            return Some(c);
+        }
+        let source = package.source.as_ref()?;
+        let key = format!("checksum {} {} ({source})", package.name, package.version);
+        self.metadata.get(&key)?.parse().ok()
+    }
+}
+
+// MARK: report
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct PackageId {
+    name: String,
+    version: String,
+}
+
+impl PackageId {
+    fn of(package: &Package) -> Self {
+        Self {
+            name: package.name.clone(),
+            version: package.version.clone(),
+        }
+    }
+}
+
+/// A crate locked at more than one version
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Duplicate {
+    name: String,
+    versions: Vec<String>,
+}
+
+/// A package which does not come from crates.io
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ForeignPackage {
+    #[serde(flatten)]
+    id: PackageId,
+    source: Source,
+    kind: SourceKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct ChecksumSummary {
+    present: usize,
+    /// registry packages without a checksum; path and git packages never have one
+    missing: Vec<PackageId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Report {
+    packages: usize,
+    duplicates: Vec<Duplicate>,
+    foreign_sources: Vec<ForeignPackage>,
+    checksums: ChecksumSummary,
+}
+
+impl Report {
+    pub fn of(lockfile: &Lockfile) -> Self {
+        let packages = lockfile.packages();
+        let mut versions: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for p in packages {
+            versions.entry(&p.name).or_default().insert(&p.version);
+        }
+        let duplicates = versions
+            .into_iter()
+            .filter(|(_, v)| v.len() > 1)
+            .map(|(name, v)| Duplicate {
+                name: name.to_string(),
+                versions: v.into_iter().map(str::to_string).collect(),
+            })
+            .collect();
+        let foreign_sources = packages
+            .iter()
+            .filter_map(|p| {
+                let source = p.source.as_ref()?;
+                let kind = source.kind();
+                (kind != SourceKind::CratesIo).then(|| ForeignPackage {
+                    id: PackageId::of(p),
+                    source: source.clone(),
+                    kind,
+                })
+            })
+            .collect();
+        let mut checksums = ChecksumSummary::default();
+        for p in packages {
+            let kind = p.source.as_ref().map(Source::kind);
+            match lockfile.checksum_of(p) {
+                Some(_) => checksums.present += 1,
+                None if matches!(kind, Some(SourceKind::CratesIo | SourceKind::Registry)) => {
+                    checksums.missing.push(PackageId::of(p));
+                }
+                None => {}
+            }
+        }
+        Self {
+            packages: packages.len(),
+            duplicates,
+            foreign_sources,
+            checksums,
+        }
+    }
+}
+
+// MARK: swatches
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    /// checksums as colour swatches
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct ReportQuery {
+    #[serde(default)]
+    pub(super) format: ReportFormat,
+}
+
+fn escape_html(s: &str) -> String {
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut acc, c| {
+            match c {
+                '&' => acc.push_str("&amp;"),
+                '<' => acc.push_str("&lt;"),
+                '>' => acc.push_str("&gt;"),
+                '"' => acc.push_str("&quot;"),
+                '\'' => acc.push_str("&#39;"),
+                c => acc.push(c),
+            }
+            acc
+        })
+}
+
+/// HTML page with a swatch per package checksum
+pub fn swatches(lockfile: &Lockfile) -> String {
+    let mut html = String::from(concat!(
+        "<!DOCTYPE html>\n",
+        "<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Cargo.lock checksums</title>\n",
+        "<style>.swatch{display:inline-block;width:4em;height:4em;margin:2px;}</style>\n",
+        "</head>\n<body>\n",
+    ));
+    for p in lockfile.packages() {
+        let Some(checksum) = lockfile.checksum_of(p) else {
+            continue;
+        };
+        let title = escape_html(&format!("{} {}", p.name, p.version));
+        let color = checksum.color();
+        writeln!(
+            html,
+            r#"<div class="swatch" style="background-color:{color};" title="{title}"></div>"#
+        )
+        .unwrap();
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
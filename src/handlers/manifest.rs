@@ -1,11 +1,18 @@
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+use crate::keyword_policy::{KeywordPolicy, PolicyViolation};
+use crate::{catalog, submissions};
+
+#[derive(Clone)]
 pub struct State {
-    pub(super) manifest_keyword: String,
+    pub(super) keyword_policy: KeywordPolicy,
+    pub(super) submissions: submissions::Repository,
+    pub(super) catalog: catalog::Repository,
 }
 
 impl State {
@@ -14,9 +21,11 @@ impl State {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Hash, Deserialize, Serialize)]
-pub struct Builder<ManifestKeyword = ()> {
-    manifest_keyword: ManifestKeyword,
+#[derive(Clone, Default)]
+pub struct Builder<Policy = (), Submissions = (), Catalog = ()> {
+    keyword_policy: Policy,
+    submissions: Submissions,
+    catalog: Catalog,
 }
 
 #[allow(dead_code)]
@@ -24,28 +33,78 @@ impl Builder {
     pub fn new() -> Self {
         Self::default()
     }
-
-    // pub fn set_manifest_keyword(self, value: Option<String>) -> Self {
-    //     Self {
-    //         manifest_keyword: value,
-    //     }
-    // }
 }
 
-impl<ManifestKeyword> Builder<ManifestKeyword> {
-    pub fn manifest_keyword<'s, S>(self, value: S) -> Builder<String>
+impl<Policy, Submissions, Catalog> Builder<Policy, Submissions, Catalog> {
+    /// Shorthand of [`KeywordPolicy::exact`]
+    pub fn manifest_keyword<'s, S>(self, value: S) -> Builder<KeywordPolicy, Submissions, Catalog>
     where
         S: Into<Cow<'s, str>>,
     {
-        let manifest_keyword = value.into().into_owned();
-        Builder { manifest_keyword }
+        self.keyword_policy(KeywordPolicy::exact(value.into().into_owned()))
+    }
+
+    pub fn keyword_policy(
+        self,
+        value: KeywordPolicy,
+    ) -> Builder<KeywordPolicy, Submissions, Catalog> {
+        let Self {
+            submissions,
+            catalog,
+            ..
+        } = self;
+        Builder {
+            keyword_policy: value,
+            submissions,
+            catalog,
+        }
+    }
+
+    pub fn submissions(
+        self,
+        value: submissions::Repository,
+    ) -> Builder<Policy, submissions::Repository, Catalog> {
+        let Self {
+            keyword_policy,
+            catalog,
+            ..
+        } = self;
+        Builder {
+            keyword_policy,
+            submissions: value,
+            catalog,
+        }
+    }
+
+    pub fn catalog(
+        self,
+        value: catalog::Repository,
+    ) -> Builder<Policy, Submissions, catalog::Repository> {
+        let Self {
+            keyword_policy,
+            submissions,
+            ..
+        } = self;
+        Builder {
+            keyword_policy,
+            submissions,
+            catalog: value,
+        }
     }
 }
 
-impl Builder<String> {
+impl Builder<KeywordPolicy, submissions::Repository, catalog::Repository> {
     pub fn build(self) -> State {
-        let Self { manifest_keyword } = self;
-        State { manifest_keyword }
+        let Self {
+            keyword_policy,
+            submissions,
+            catalog,
+        } = self;
+        State {
+            keyword_policy,
+            submissions,
+            catalog,
+        }
     }
 }
 
@@ -67,23 +126,48 @@ pub(super) struct ProperOrder {
 
 impl ProperOrder {
     pub(super) fn from_value(value: &toml::Value) -> Option<Vec<Self>> {
-        let orders = value
-            .as_table()?
-            .get("orders")?
-            .as_array()?
-            .iter()
-            .filter_map(|o| {
-                let table = o.as_table()?;
-                let item = table.get("item")?.as_str()?.to_string();
-                let quantity = table.get("quantity")?.as_integer()?;
-                let quantity = u32::try_from(quantity).ok()?;
-                let o = Self { item, quantity };
-                tracing::info!(order = ?o);
-                Some(o)
-            })
-            .collect();
+        let (orders, _) = Self::collect_from_value(value)?;
         Some(orders)
     }
+
+    /// Same as [`Self::from_value`], but also returns entries which were dropped
+    pub(super) fn collect_from_value(
+        value: &toml::Value,
+    ) -> Option<(Vec<Self>, Vec<DroppedOrder>)> {
+        let entries = value.as_table()?.get("orders")?.as_array()?;
+        let mut orders = Vec::with_capacity(entries.len());
+        let mut dropped = vec![];
+        for (index, entry) in entries.iter().enumerate() {
+            match Self::from_entry(entry) {
+                Ok(o) => {
+                    tracing::info!(order = ?o);
+                    orders.push(o);
+                }
+                Err(reason) => {
+                    let d = DroppedOrder { index, reason };
+                    tracing::info!(dropped = ?d);
+                    dropped.push(d);
+                }
+            }
+        }
+        Some((orders, dropped))
+    }
+
+    fn from_entry(entry: &toml::Value) -> Result<Self, DropReason> {
+        let table = entry.as_table().ok_or(DropReason::NotATable)?;
+        let item = table
+            .get("item")
+            .and_then(toml::Value::as_str)
+            .ok_or(DropReason::MissingItem)?
+            .to_string();
+        let quantity = table
+            .get("quantity")
+            .ok_or(DropReason::MissingQuantity)?
+            .as_integer()
+            .ok_or(DropReason::NonIntegerQuantity)?;
+        let quantity = u32::try_from(quantity).map_err(|_| DropReason::QuantityOutOfRange)?;
+        Ok(Self { item, quantity })
+    }
 }
 
 impl fmt::Display for ProperOrder {
@@ -93,15 +177,944 @@ impl fmt::Display for ProperOrder {
     }
 }
 
-pub(super) fn manifest_key_included(state: &State, manifest: &Manifest) -> bool {
-    let keyword = &state.manifest_keyword;
-    let manifest_keywords = manifest.package.as_ref().and_then(|p| p.keywords.as_ref());
-    let Some(manifest_keywords) = manifest_keywords else {
-        return false;
+// MARK: aggregation
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Item,
+    Quantity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct OrderQuery {
+    /// sum up quantities of the same item
+    #[serde(default)]
+    pub(super) merge: bool,
+    #[serde(default)]
+    pub(super) sort: Option<SortKey>,
+    #[serde(default)]
+    pub(super) order: SortOrder,
+    #[serde(default)]
+    pub(super) format: OutputFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Total quantity of {item} overflowed")]
+pub struct QuantityOverflow {
+    item: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub(super) struct OrdersResponse {
+    orders: Orders,
+    total_quantity: u64,
+    invoice: Invoice,
+}
+
+impl OrdersResponse {
+    pub(super) fn new(orders: Orders, invoice: Invoice) -> Self {
+        let total_quantity = orders.iter().map(|o| u64::from(o.quantity)).sum();
+        Self {
+            orders,
+            total_quantity,
+            invoice,
+        }
+    }
+}
+
+impl OrderQuery {
+    pub(super) fn apply(&self, orders: Orders) -> Result<Orders, QuantityOverflow> {
+        let mut orders = if self.merge {
+            merge_orders(orders)?
+        } else {
+            orders
+        };
+        if let Some(key) = self.sort {
+            sort_orders(&mut orders, key, self.order);
+        }
+        Ok(orders)
+    }
+}
+
+/// Merge orders of the same item, keeping the position of first appearance
+fn merge_orders(orders: Orders) -> Result<Orders, QuantityOverflow> {
+    let mut merged: Orders = Vec::with_capacity(orders.len());
+    let mut positions: HashMap<String, usize> = HashMap::with_capacity(orders.len());
+    for order in orders {
+        let Some(&at) = positions.get(&order.item) else {
+            positions.insert(order.item.clone(), merged.len());
+            merged.push(order);
+            continue;
+        };
+        let m = &mut merged[at];
+        m.quantity = m
+            .quantity
+            .checked_add(order.quantity)
+            .ok_or_else(|| QuantityOverflow {
+                item: order.item.clone(),
+            })?;
+    }
+    Ok(merged)
+}
+
+/// Stable in both directions, so equal keys keep their input order
+fn sort_orders(orders: &mut Orders, key: SortKey, order: SortOrder) {
+    let compare = |l: &ProperOrder, r: &ProperOrder| match key {
+        SortKey::Item => l.item.cmp(&r.item),
+        SortKey::Quantity => l.quantity.cmp(&r.quantity),
+    };
+    match order {
+        SortOrder::Asc => orders.sort_by(compare),
+        SortOrder::Desc => orders.sort_by(|l, r| compare(r, l)),
+    }
+}
+
+// MARK: report
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    NotATable,
+    MissingItem,
+    MissingQuantity,
+    NonIntegerQuantity,
+    QuantityOutOfRange,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct DroppedOrder {
+    index: usize,
+    reason: DropReason,
+}
+
+/// 1-based line and column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// position of byte `offset` in `source`
+    fn at(source: &str, offset: usize) -> Self {
+        let offset = offset.min(source.len());
+        let head = String::from_utf8_lossy(&source.as_bytes()[..offset]);
+        let (line, column) =
+            head.chars().fold(
+                (1, 1),
+                |(l, c), ch| {
+                    if ch == '\n' {
+                        (l + 1, 1)
+                    } else {
+                        (l, c + 1)
+                    }
+                },
+            );
+        Self { line, column }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Span {
+    start: Position,
+    end: Position,
+}
+
+impl Span {
+    fn point(at: Position) -> Self {
+        Self { start: at, end: at }
+    }
+
+    fn of_range(source: &str, range: std::ops::Range<usize>) -> Self {
+        let start = Position::at(source, range.start);
+        let end = Position::at(source, range.end);
+        Self { start, end }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Problem {
+    /// part of a multipart upload the problem is in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    part: Option<String>,
+    message: String,
+    span: Option<Span>,
+}
+
+impl Problem {
+    fn new<'s, S>(message: S, span: Option<Span>) -> Self
+    where
+        S: Into<Cow<'s, str>>,
+    {
+        let message = message.into().into_owned();
+        Self {
+            part: None,
+            message,
+            span,
+        }
+    }
+
+    fn in_part(self, part: &str) -> Self {
+        Self {
+            part: Some(part.to_string()),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// Format of the media type, whatever its parameters such as `charset`
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type: mime::Mime = content_type.parse().ok()?;
+        match media_type.essence_str() {
+            "application/toml" => Some(Self::Toml),
+            "application/json" => Some(Self::Json),
+            "application/yaml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    fn parse<T: DeserializeOwned>(self, source: &str) -> Result<T, Problem> {
+        match self {
+            Self::Toml => toml::from_str(source).map_err(|e| {
+                let span = e.span().map(|r| Span::of_range(source, r));
+                Problem::new(e.message(), span)
+            }),
+            Self::Json => serde_json::from_str(source).map_err(|e| {
+                let span = Span::point(Position::new(e.line(), e.column()));
+                Problem::new(e.to_string(), Some(span))
+            }),
+            Self::Yaml => serde_yaml::from_str(source).map_err(|e| {
+                let span = e
+                    .location()
+                    .map(|l| Span::point(Position::new(l.line(), l.column())));
+                Problem::new(e.to_string(), span)
+            }),
+        }
+    }
+}
+
+/// Verbose result of validating a manifest
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct Report {
+    problems: Vec<Problem>,
+    orders: Orders,
+    dropped_orders: Vec<DroppedOrder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct ReportQuery {
+    #[serde(default)]
+    pub(super) verbose: bool,
+}
+
+impl ReportQuery {
+    pub(crate) fn is_verbose(&self) -> bool {
+        self.verbose
+    }
+}
+
+impl Report {
+    pub(super) fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub(super) fn build(state: &State, content_type: &str, body: &[u8]) -> Self {
+        let mut report = Self::default();
+        let Some(format) = Format::from_content_type(content_type) else {
+            let message = format!("Unsupported content type {content_type:?}");
+            report.problems.push(Problem::new(message, None));
+            return report;
+        };
+        match Self::parse(format, body) {
+            Ok(manifest) => report.check(state, &manifest),
+            Err(p) => report.problems.push(p),
+        }
+        report
+    }
+
+    /// Same as [`Self::build`] for a member manifest uploaded with its workspace root manifest
+    pub(super) fn build_workspace(
+        state: &State,
+        member: Option<&[u8]>,
+        root: Option<&[u8]>,
+    ) -> Self {
+        fn parse_part<T: DeserializeOwned>(
+            report: &mut Report,
+            part: &str,
+            body: Option<&[u8]>,
+        ) -> Option<T> {
+            let Some(body) = body else {
+                let message = format!("multipart form has no part named {part:?}");
+                report
+                    .problems
+                    .push(Problem::new(message, None).in_part(part));
+                return None;
+            };
+            Report::parse(Format::Toml, body)
+                .map_err(|p| report.problems.push(p.in_part(part)))
+                .ok()
+        }
+
+        let mut report = Self::default();
+        let member: Option<Manifest> = parse_part(&mut report, "manifest", member);
+        let root: Option<WorkspaceRoot> = parse_part(&mut report, "workspace", root);
+        if let (Some(member), Some(root)) = (member, root) {
+            let manifest = inherit_workspace(member, &root);
+            report.check(state, &manifest);
+        }
+        report
+    }
+
+    fn parse<T: DeserializeOwned>(format: Format, body: &[u8]) -> Result<T, Problem> {
+        let source = match std::str::from_utf8(body) {
+            Ok(s) => s,
+            Err(e) => {
+                let valid = std::str::from_utf8(&body[..e.valid_up_to()]).unwrap();
+                let span = Span::point(Position::at(valid, valid.len()));
+                return Err(Problem::new(e.to_string(), Some(span)));
+            }
+        };
+        format.parse(source)
+    }
+
+    /// Keyword policy and orders of a parsed manifest
+    fn check(&mut self, state: &State, manifest: &Manifest) {
+        if let Err(e) = check_keywords(state, manifest) {
+            tracing::info!(
+                err = &e as &dyn std::error::Error,
+                "keyword policy violated"
+            );
+            let problem = Problem::new(format!("Magic keyword not provided: {e}"), None);
+            self.problems.push(problem);
+        }
+        let collected = manifest
+            .package
+            .as_ref()
+            .and_then(|p| p.metadata.as_ref())
+            .and_then(ProperOrder::collect_from_value);
+        if let Some((orders, dropped)) = collected {
+            self.orders = orders;
+            self.dropped_orders = dropped;
+        }
+    }
+}
+
+// MARK: invoice
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineStatus {
+    Available,
+    UnknownItem,
+    InsufficientStock,
+}
+
+impl fmt::Display for LineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Available => f.write_str("available"),
+            Self::UnknownItem => f.write_str("unknown item"),
+            Self::InsufficientStock => f.write_str("insufficient stock"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct InvoiceLine {
+    item: String,
+    quantity: u32,
+    unit_price: Option<i64>,
+    line_total: Option<i128>,
+    status: LineStatus,
+}
+
+impl fmt::Display for InvoiceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { item, status, .. } = self;
+        write!(f, "{item}: {status}")
+    }
+}
+
+/// Orders priced against the catalog
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct Invoice {
+    lines: Vec<InvoiceLine>,
+    total: i128,
+    /// whether the stock has been reserved
+    reserved: bool,
+}
+
+impl Invoice {
+    pub(super) async fn of(state: &State, orders: &Orders) -> sqlx::Result<Self> {
+        use catalog::model::ItemName;
+
+        let requested = Self::requested(orders);
+        let names: Vec<_> = requested.keys().cloned().collect();
+        let items = state.catalog.find_many(&names).await?;
+        let lines: Vec<_> = orders
+            .iter()
+            .map(|o| {
+                let name = ItemName(o.item.clone());
+                let Some(entry) = items.get(&name) else {
+                    return InvoiceLine {
+                        item: o.item.clone(),
+                        quantity: o.quantity,
+                        unit_price: None,
+                        line_total: None,
+                        status: LineStatus::UnknownItem,
+                    };
+                };
+                let status = if requested[&name] <= entry.stock.0 {
+                    LineStatus::Available
+                } else {
+                    LineStatus::InsufficientStock
+                };
+                let unit_price = entry.unit_price.0;
+                InvoiceLine {
+                    item: o.item.clone(),
+                    quantity: o.quantity,
+                    unit_price: Some(unit_price),
+                    line_total: Some(i128::from(unit_price) * i128::from(o.quantity)),
+                    status,
+                }
+            })
+            .collect();
+        let total = lines.iter().filter_map(|l| l.line_total).sum();
+        let invoice = Self {
+            lines,
+            total,
+            reserved: false,
+        };
+        Ok(invoice)
+    }
+
+    /// total quantity per item
+    fn requested(orders: &Orders) -> HashMap<catalog::model::ItemName, i64> {
+        let mut requested = HashMap::new();
+        for o in orders {
+            let name = catalog::model::ItemName(o.item.clone());
+            *requested.entry(name).or_default() += i64::from(o.quantity);
+        }
+        requested
+    }
+
+    pub(super) fn is_available(&self) -> bool {
+        self.lines.iter().all(|l| l.status == LineStatus::Available)
+    }
+
+    pub(super) fn unavailable_lines(&self) -> impl Iterator<Item = &InvoiceLine> {
+        self.lines
+            .iter()
+            .filter(|l| l.status != LineStatus::Available)
+    }
+
+    pub(super) async fn reserve(
+        &mut self,
+        state: &State,
+        orders: &Orders,
+    ) -> Result<(), catalog::ops::ReserveError> {
+        use catalog::model::Stock;
+
+        let requests: Vec<_> = Self::requested(orders)
+            .into_iter()
+            .map(|(n, q)| (n, Stock(q)))
+            .collect();
+        state.catalog.reserve(&requests).await?;
+        self.reserved = true;
+        Ok(())
+    }
+
+    /// Give back what [`Self::reserve`] took, if anything
+    pub(super) async fn release(&mut self, state: &State, orders: &Orders) -> sqlx::Result<()> {
+        use catalog::model::Stock;
+
+        if !self.reserved {
+            return Ok(());
+        }
+        let requests: Vec<_> = Self::requested(orders)
+            .into_iter()
+            .map(|(n, q)| (n, Stock(q)))
+            .collect();
+        state.catalog.release(&requests).await?;
+        self.reserved = false;
+        Ok(())
+    }
+}
+
+/// `package.metadata.confirm = true`
+pub(super) fn confirm_requested(manifest: &Manifest) -> bool {
+    manifest
+        .package
+        .as_ref()
+        .and_then(|p| p.metadata.as_ref())
+        .and_then(|m| m.get("confirm"))
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(false)
+}
+
+// MARK: submissions
+
+impl From<ProperOrder> for submissions::model::Order {
+    fn from(value: ProperOrder) -> Self {
+        use submissions::model::{Item, Quantity};
+
+        let ProperOrder { item, quantity } = value;
+        Self {
+            item: Item(item),
+            quantity: Quantity(quantity.into()),
+        }
+    }
+}
+
+pub(super) fn submission_of(
+    manifest: &Manifest,
+    orders: Orders,
+) -> Option<submissions::ops::CreateRequest> {
+    use cargo_manifest::MaybeInherited;
+    use submissions::model::{PackageName, PackageVersion};
+
+    let package = manifest.package.as_ref()?;
+    let package_version = match package.version() {
+        MaybeInherited::Local(v) => Some(PackageVersion(v.to_string())),
+        MaybeInherited::Inherited { .. } => None,
+    };
+    let request = submissions::ops::CreateRequest {
+        package_name: PackageName(package.name.clone()),
+        package_version,
+        orders: orders.into_iter().map(Into::into).collect(),
+    };
+    Some(request)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct SubmissionListQuery {
+    pub(super) page: Option<u64>,
+    pub(super) per_page: Option<u64>,
+}
+
+impl SubmissionListQuery {
+    const DEFAULT_PER_PAGE: u64 = 20;
+    const MAX_PER_PAGE: u64 = 100;
+
+    /// Fails when the offset of the page does not fit in a database integer
+    pub(super) fn page(&self) -> Result<u64, PageOutOfRange> {
+        let page = self.page.unwrap_or(1).max(1);
+        (page - 1)
+            .checked_mul(self.per_page())
+            .and_then(|offset| i64::try_from(offset).ok())
+            .map(|_| page)
+            .ok_or(PageOutOfRange(page))
+    }
+
+    pub(super) fn per_page(&self) -> u64 {
+        self.per_page
+            .unwrap_or(Self::DEFAULT_PER_PAGE)
+            .clamp(1, Self::MAX_PER_PAGE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("Page {0} is out of range")]
+pub struct PageOutOfRange(u64);
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub(crate) struct SubmissionPathParam {
+    pub(super) id: submissions::model::SubmissionId,
+}
+
+impl SubmissionPathParam {
+    pub(crate) fn new(id: uuid::Uuid) -> Self {
+        Self {
+            id: submissions::model::SubmissionId(id),
+        }
+    }
+}
+
+pub type TotalsQuery = submissions::ops::DateRange;
+
+// MARK: workspace
+
+/// The `[workspace.package]` table of a workspace root manifest
+///
+/// Read on its own, as [`cargo_manifest::WorkspacePackage`] has no `metadata`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct WorkspaceRoot {
+    workspace: Option<WorkspaceTable>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+struct WorkspaceTable {
+    package: Option<WorkspacePackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+struct WorkspacePackage {
+    version: Option<String>,
+    keywords: Option<Vec<String>>,
+    categories: Option<Vec<String>>,
+    metadata: Option<toml::Table>,
+}
+
+/// Resolve `member` against the `[workspace.package]` table of `root`
+///
+/// `version`, `keywords` and `categories` declared as `{ workspace = true }` are replaced with
+/// the workspace values. `metadata` is merged with `[workspace.package.metadata]`, with keys of
+/// the member table taking precedence.
+pub fn inherit_workspace(mut member: Manifest, root: &WorkspaceRoot) -> Manifest {
+    use cargo_manifest::MaybeInherited;
+
+    let Some(ws_package) = root.workspace.as_ref().and_then(|w| w.package.as_ref()) else {
+        return member;
     };
-    let Some(manifest_keywords) = manifest_keywords.as_ref().as_local() else {
-        // TODO
-        return false;
+    let Some(package) = member.package.as_mut() else {
+        return member;
     };
-    manifest_keywords.contains(keyword)
+    if let (Some(MaybeInherited::Inherited { .. }), Some(version)) =
+        (&package.version, &ws_package.version)
+    {
+        package.version = Some(MaybeInherited::Local(version.clone()));
+    }
+    if let (Some(MaybeInherited::Inherited { .. }), Some(keywords)) =
+        (&package.keywords, &ws_package.keywords)
+    {
+        package.keywords = Some(MaybeInherited::Local(keywords.clone()));
+    }
+    if let (Some(MaybeInherited::Inherited { .. }), Some(categories)) =
+        (&package.categories, &ws_package.categories)
+    {
+        package.categories = Some(MaybeInherited::Local(categories.clone()));
+    }
+    if let Some(ws_metadata) = ws_package.metadata.as_ref() {
+        let metadata = package
+            .metadata
+            .get_or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let Some(metadata) = metadata.as_table_mut() {
+            for (key, value) in ws_metadata {
+                metadata.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+    member
+}
+
+pub(super) fn check_keywords(state: &State, manifest: &Manifest) -> Result<(), PolicyViolation> {
+    state.keyword_policy.check(manifest)
+}
+
+// MARK: diff
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FieldChange {
+    field: String,
+    old: Option<toml::Value>,
+    new: Option<toml::Value>,
+}
+
+/// Changes between top-level keys of two tables, except for `skip`
+fn diff_tables(old: &toml::Table, new: &toml::Table, skip: &[&str]) -> Vec<FieldChange> {
+    let fields: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+    fields
+        .into_iter()
+        .filter(|f| !skip.contains(&f.as_str()))
+        .filter_map(|field| {
+            let (old, new) = (old.get(field), new.get(field));
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                old: old.cloned(),
+                new: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct DependencyEntry {
+    /// e.g. `dependencies`, `dev-dependencies`, `target.'cfg(unix)'.dependencies`
+    section: String,
+    name: String,
+    version: String,
+    features: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct VersionChange {
+    section: String,
+    name: String,
+    old: String,
+    new: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct FeaturesChange {
+    section: String,
+    name: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct DependencyDiff {
+    added: Vec<DependencyEntry>,
+    removed: Vec<DependencyEntry>,
+    version_changed: Vec<VersionChange>,
+    features_changed: Vec<FeaturesChange>,
+}
+
+type DependencySections<'a> = BTreeMap<(String, &'a str), &'a cargo_manifest::Dependency>;
+
+fn dependency_sections(manifest: &Manifest) -> DependencySections<'_> {
+    let targets = manifest.target.iter().flatten().flat_map(|(cfg, target)| {
+        [
+            (
+                format!("target.'{cfg}'.dependencies"),
+                Some(&target.dependencies),
+            ),
+            (
+                format!("target.'{cfg}'.dev-dependencies"),
+                Some(&target.dev_dependencies),
+            ),
+            (
+                format!("target.'{cfg}'.build-dependencies"),
+                Some(&target.build_dependencies),
+            ),
+        ]
+    });
+    let sections = [
+        ("dependencies".to_string(), manifest.dependencies.as_ref()),
+        (
+            "dev-dependencies".to_string(),
+            manifest.dev_dependencies.as_ref(),
+        ),
+        (
+            "build-dependencies".to_string(),
+            manifest.build_dependencies.as_ref(),
+        ),
+    ]
+    .into_iter()
+    .chain(targets);
+    let mut dependencies = DependencySections::new();
+    for (section, deps) in sections {
+        for (name, dep) in deps.into_iter().flatten() {
+            dependencies.insert((section.clone(), name.as_str()), dep);
+        }
+    }
+    dependencies
+}
+
+impl DependencyDiff {
+    fn between(old: &Manifest, new: &Manifest) -> Self {
+        let entry =
+            |(section, name): &(String, &str), dep: &cargo_manifest::Dependency| DependencyEntry {
+                section: section.clone(),
+                name: name.to_string(),
+                version: dep.req().to_string(),
+                features: dep.req_features().to_vec(),
+            };
+        let (old, new) = (dependency_sections(old), dependency_sections(new));
+        let mut diff = Self::default();
+        for (key, old_dep) in &old {
+            let Some(new_dep) = new.get(key) else {
+                diff.removed.push(entry(key, old_dep));
+                continue;
+            };
+            let (section, name) = (key.0.clone(), key.1.to_string());
+            if old_dep.req() != new_dep.req() {
+                diff.version_changed.push(VersionChange {
+                    section: section.clone(),
+                    name: name.clone(),
+                    old: old_dep.req().to_string(),
+                    new: new_dep.req().to_string(),
+                });
+            }
+            let old_features: BTreeSet<_> = old_dep.req_features().iter().collect();
+            let new_features: BTreeSet<_> = new_dep.req_features().iter().collect();
+            if old_features != new_features {
+                diff.features_changed.push(FeaturesChange {
+                    section,
+                    name,
+                    added: new_features
+                        .difference(&old_features)
+                        .map(|f| f.to_string())
+                        .collect(),
+                    removed: old_features
+                        .difference(&new_features)
+                        .map(|f| f.to_string())
+                        .collect(),
+                });
+            }
+        }
+        for (key, new_dep) in &new {
+            if !old.contains_key(key) {
+                diff.added.push(entry(key, new_dep));
+            }
+        }
+        diff
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct KeywordsDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl KeywordsDiff {
+    fn between(old: &Manifest, new: &Manifest) -> Self {
+        fn keywords(manifest: &Manifest) -> Vec<String> {
+            manifest
+                .package
+                .as_ref()
+                .and_then(|p| p.keywords.clone())
+                .and_then(|k| k.as_local())
+                .unwrap_or_default()
+        }
+
+        let (old, new) = (keywords(old), keywords(new));
+        Self {
+            added: new.iter().filter(|k| !old.contains(k)).cloned().collect(),
+            removed: old.iter().filter(|k| !new.contains(k)).cloned().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct OrderQuantity {
+    item: String,
+    quantity: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct QuantityChange {
+    item: String,
+    old: u64,
+    new: u64,
+}
+
+/// Differences of total quantities per item
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct OrdersDiff {
+    added: Vec<OrderQuantity>,
+    removed: Vec<OrderQuantity>,
+    quantity_changed: Vec<QuantityChange>,
+}
+
+impl OrdersDiff {
+    fn between(old: &Manifest, new: &Manifest) -> Self {
+        fn totals(manifest: &Manifest) -> BTreeMap<String, u64> {
+            let orders = manifest
+                .package
+                .as_ref()
+                .and_then(|p| p.metadata.as_ref())
+                .and_then(ProperOrder::from_value)
+                .unwrap_or_default();
+            let mut totals = BTreeMap::new();
+            for ProperOrder { item, quantity } in orders {
+                *totals.entry(item).or_default() += u64::from(quantity);
+            }
+            totals
+        }
+
+        let (old, new) = (totals(old), totals(new));
+        let mut diff = Self::default();
+        for (item, &quantity) in &old {
+            match new.get(item) {
+                None => diff.removed.push(OrderQuantity {
+                    item: item.clone(),
+                    quantity,
+                }),
+                Some(&n) if n != quantity => diff.quantity_changed.push(QuantityChange {
+                    item: item.clone(),
+                    old: quantity,
+                    new: n,
+                }),
+                Some(_) => {}
+            }
+        }
+        for (item, &quantity) in &new {
+            if !old.contains_key(item) {
+                diff.added.push(OrderQuantity {
+                    item: item.clone(),
+                    quantity,
+                });
+            }
+        }
+        diff
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ManifestDiff {
+    dependencies: DependencyDiff,
+    /// `[package]` fields other than `keywords` and `metadata`
+    package: Vec<FieldChange>,
+    keywords: KeywordsDiff,
+    /// `[package.metadata]` keys other than `orders`
+    metadata: Vec<FieldChange>,
+    orders: OrdersDiff,
+}
+
+impl ManifestDiff {
+    pub fn between(old: &Manifest, new: &Manifest) -> Result<Self, toml::ser::Error> {
+        fn package_table(manifest: &Manifest) -> Result<toml::Table, toml::ser::Error> {
+            match &manifest.package {
+                Some(p) => toml::Table::try_from(p),
+                None => Ok(toml::Table::new()),
+            }
+        }
+
+        fn metadata_table(manifest: &Manifest) -> toml::Table {
+            manifest
+                .package
+                .as_ref()
+                .and_then(|p| p.metadata.as_ref())
+                .and_then(toml::Value::as_table)
+                .cloned()
+                .unwrap_or_default()
+        }
+
+        let package = diff_tables(
+            &package_table(old)?,
+            &package_table(new)?,
+            &["keywords", "metadata"],
+        );
+        let metadata = diff_tables(&metadata_table(old), &metadata_table(new), &["orders"]);
+        let diff = Self {
+            dependencies: DependencyDiff::between(old, new),
+            package,
+            keywords: KeywordsDiff::between(old, new),
+            metadata,
+            orders: OrdersDiff::between(old, new),
+        };
+        Ok(diff)
+    }
 }
@@ -1,20 +1,34 @@
 use std::future::Future;
+use std::net::SocketAddr;
 use std::ops::ControlFlow;
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use warp::{http, hyper};
 
-use crate::bucket::{milk, Gallons, Liters, Litres, MilkBucket, Pints};
+use super::auth_token;
+use crate::bucket::clients::{BucketStatus, ClientKey, KeyBy};
+use crate::bucket::limiter::{Algorithm, RateLimiter};
+use crate::bucket::unit::{
+    widen, Exact, InvalidQuantity, InvalidRounding, Quantity, Rounding, Volume, VolumeUnit,
+    MILLILITERS,
+};
+use crate::bucket::{milk, ClientBuckets, Gallons, Liters, Litres, Pints};
 
 #[derive(Debug, Clone)]
 pub struct State {
-    pub(super) bucket: MilkBucket,
+    /// of [`ClientKey::Global`]
+    pub(super) limiter: Arc<dyn RateLimiter>,
+    pub(super) clients: ClientBuckets,
+    pub(super) admin_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct Builder<Bucket = ()> {
-    bucket: Bucket,
+pub struct Builder<Limiter = (), Clients = ()> {
+    limiter: Limiter,
+    clients: Clients,
+    admin_token: Option<String>,
 }
 
 impl Builder {
@@ -23,16 +37,54 @@ impl Builder {
     }
 }
 
-impl<Bucket> Builder<Bucket> {
-    pub fn bucket(self, value: MilkBucket) -> Builder<MilkBucket> {
-        Builder { bucket: value }
+impl<Limiter, Clients> Builder<Limiter, Clients> {
+    pub fn limiter(self, value: Arc<dyn RateLimiter>) -> Builder<Arc<dyn RateLimiter>, Clients> {
+        let Self {
+            clients,
+            admin_token,
+            ..
+        } = self;
+        Builder {
+            limiter: value,
+            clients,
+            admin_token,
+        }
+    }
+
+    pub fn clients(self, value: ClientBuckets) -> Builder<Limiter, ClientBuckets> {
+        let Self {
+            limiter,
+            admin_token,
+            ..
+        } = self;
+        Builder {
+            limiter,
+            clients: value,
+            admin_token,
+        }
+    }
+
+    /// Bearer token for admin endpoints, which are disabled without one
+    pub fn admin_token(self, value: Option<String>) -> Self {
+        Self {
+            admin_token: value,
+            ..self
+        }
     }
 }
 
-impl Builder<MilkBucket> {
+impl Builder<Arc<dyn RateLimiter>, ClientBuckets> {
     pub fn build(self) -> State {
-        let Self { bucket } = self;
-        State { bucket }
+        let Self {
+            limiter,
+            clients,
+            admin_token,
+        } = self;
+        State {
+            limiter,
+            clients,
+            admin_token,
+        }
     }
 }
 
@@ -43,24 +95,415 @@ impl State {
 }
 
 impl State {
-    pub fn refill_task(&self, rate: milk::RefillRate) -> impl Future<Output = ()> + Send + 'static {
-        self.bucket.clone().refill_task(rate)
+    /// refill task of the global limiter if it is [`Algorithm::Bucket`]
+    pub fn refill_task(&self) -> impl Future<Output = ()> + Send + 'static {
+        let bucket = self.limiter.as_bucket().cloned();
+        async move {
+            if let Some(bucket) = bucket {
+                bucket.refill_task().await;
+            }
+        }
+    }
+
+    pub fn evict_task(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.clients.clone().evict_task()
+    }
+
+    pub(super) async fn limiter_for(&self, key: ClientKey) -> Arc<dyn RateLimiter> {
+        match key {
+            ClientKey::Global => Arc::clone(&self.limiter),
+            key => match self.clients.get(key).await {
+                Some(limiter) => limiter,
+                None => {
+                    tracing::warn!("sharing the global bucket with a new client");
+                    Arc::clone(&self.limiter)
+                }
+            },
+        }
     }
 }
 
-pub async fn check_bucket(state: Arc<State>) -> ControlFlow<super::Response> {
-    if !state.bucket.is_empty().await {
-        return ControlFlow::Continue(());
+pub(crate) async fn client_key(
+    state: &State,
+    auth: &auth_token::State,
+    remote: Option<SocketAddr>,
+    headers: &http::HeaderMap,
+) -> ClientKey {
+    let by_addr = || match remote {
+        Some(addr) => ClientKey::Addr(addr.ip()),
+        None => ClientKey::Global,
+    };
+    match state.clients.key_by() {
+        KeyBy::Global => ClientKey::Global,
+        KeyBy::RemoteAddr => by_addr(),
+        KeyBy::Claim(name) => {
+            let claims = match auth_token::unwrap_cookie_from_headers(auth, headers).await {
+                Ok(c) => c,
+                Err(e) => {
+                    tracing::info!(err = %e, "no claims, falling back to remote address");
+                    return by_addr();
+                }
+            };
+            let addr = remote.map(|a| a.ip());
+            match claims.get(name) {
+                Some(Value::String(s)) => state.clients.claim_key(s.clone(), addr).await,
+                Some(v) if !v.is_null() => state.clients.claim_key(v.to_string(), addr).await,
+                _ => {
+                    tracing::info!(
+                        claim = name,
+                        "claim not found, falling back to remote address"
+                    );
+                    by_addr()
+                }
+            }
+        }
     }
-    let body = hyper::Body::from("No milk available\n".to_string());
+}
+
+pub(super) fn check_admin(
+    state: &State,
+    authorization: Option<&str>,
+) -> ControlFlow<super::Response> {
+    let status = match (&state.admin_token, authorization) {
+        (None, _) => http::StatusCode::FORBIDDEN,
+        (Some(token), Some(auth))
+            if auth
+                .strip_prefix("Bearer ")
+                .is_some_and(|a| constant_time_eq(a.as_bytes(), token.as_bytes())) =>
+        {
+            return ControlFlow::Continue(());
+        }
+        (Some(_), _) => http::StatusCode::UNAUTHORIZED,
+    };
     let res = super::Response::builder()
-        .status(http::StatusCode::TOO_MANY_REQUESTS)
-        .header(http::header::CONTENT_TYPE, "plain/text")
-        .body(body)
+        .status(status)
+        .header(http::header::WWW_AUTHENTICATE, "Bearer")
+        .body(hyper::Body::empty())
         .unwrap();
     ControlFlow::Break(res)
 }
 
+/// Compares every byte regardless of where the first difference is
+fn constant_time_eq(l: &[u8], r: &[u8]) -> bool {
+    if l.len() != r.len() {
+        return false;
+    }
+    let diff = l.iter().zip(r).fold(0, |acc, (l, r)| acc | (l ^ r));
+    std::hint::black_box(diff) == 0
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct BucketsResponse {
+    algorithm: Algorithm,
+    global: Liters,
+    clients: Vec<BucketStatus>,
+}
+
+impl BucketsResponse {
+    pub(super) async fn collect(state: &State) -> Self {
+        Self {
+            algorithm: state.limiter.algorithm(),
+            global: state.limiter.quota().await.remaining(),
+            clients: state.clients.statuses().await,
+        }
+    }
+}
+
+/// Body of `GET` and `PUT /9/rate`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateBody {
+    amount: Liters,
+    interval_secs: f64,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum InvalidRate {
+    #[error("amount must be a non-negative number")]
+    Amount,
+    #[error("interval_secs must be a positive number")]
+    Interval,
+}
+
+impl From<milk::RefillRate> for RateBody {
+    fn from(value: milk::RefillRate) -> Self {
+        Self {
+            amount: value.amount(),
+            interval_secs: value.duration().as_secs_f64(),
+        }
+    }
+}
+
+impl TryFrom<RateBody> for milk::RefillRate {
+    type Error = InvalidRate;
+
+    fn try_from(value: RateBody) -> Result<Self, Self::Error> {
+        let RateBody {
+            amount,
+            interval_secs,
+        } = value;
+        if !amount.0.is_finite() || amount.0 < 0.0 {
+            return Err(InvalidRate::Amount);
+        }
+        let duration = std::time::Duration::try_from_secs_f64(interval_secs)
+            .ok()
+            .filter(|d| !d.is_zero())
+            .ok_or(InvalidRate::Interval)?;
+        Ok(Self::new(amount, duration))
+    }
+}
+
+impl State {
+    pub(super) fn refill_rate(&self) -> milk::RefillRate {
+        self.limiter.refill_rate()
+    }
+
+    /// Applies to the global limiter and every client one
+    pub(super) async fn set_refill_rate(&self, rate: milk::RefillRate) {
+        self.limiter.set_refill_rate(rate);
+        self.clients.set_refill_rate(rate).await;
+    }
+}
+
+// MARK: wait
+
+/// upper bound of `?wait=` and `Prefer: wait=`
+pub(super) const MAX_WAIT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// `?wait=5s` of `POST /9/milk`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WaitQuery {
+    wait: Option<String>,
+}
+
+/// How long a request may wait for milk, from the query or the `Prefer` header
+#[derive(Debug, Clone, Default)]
+pub struct WaitRequest {
+    query: Option<String>,
+    prefer: Option<String>,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("invalid wait {0:?}, expected seconds such as `5`, `5s` or `500ms`")]
+pub struct InvalidWait(String);
+
+impl WaitQuery {
+    pub(crate) fn with_prefer(self, prefer: Option<String>) -> WaitRequest {
+        WaitRequest {
+            query: self.wait,
+            prefer,
+        }
+    }
+}
+
+fn parse_wait(value: &str) -> Result<std::time::Duration, InvalidWait> {
+    let invalid = || InvalidWait(value.to_string());
+    let v = value.trim();
+    let (number, scale) = if let Some(ms) = v.strip_suffix("ms") {
+        (ms, 1e-3)
+    } else if let Some(s) = v.strip_suffix('s') {
+        (s, 1.0)
+    } else {
+        (v, 1.0)
+    };
+    let secs: f64 = number.trim().parse().map_err(|_| invalid())?;
+    std::time::Duration::try_from_secs_f64(secs * scale).map_err(|_| invalid())
+}
+
+impl WaitRequest {
+    /// `None` if the request should not wait, capped by [`MAX_WAIT`]
+    pub(super) fn duration(&self) -> Result<Option<std::time::Duration>, InvalidWait> {
+        // https://www.rfc-editor.org/rfc/rfc7240#section-4.3
+        let prefer = self.prefer.as_deref().and_then(|p| {
+            p.split([',', ';'])
+                .filter_map(|pref| pref.trim().split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("wait"))
+                .map(|(_, value)| value.trim().trim_matches('"'))
+        });
+        let Some(wait) = self.query.as_deref().or(prefer) else {
+            return Ok(None);
+        };
+        let wait = parse_wait(wait)?;
+        Ok(Some(wait.min(MAX_WAIT)))
+    }
+}
+
+/// [`RateLimiter::acquire_waiting`] if `wait` is given, otherwise [`RateLimiter::try_acquire`]
+pub(super) async fn withdraw(
+    limiter: &dyn RateLimiter,
+    liters: Liters,
+    wait: Option<std::time::Duration>,
+) -> Result<milk::Pack, milk::Empty> {
+    match wait {
+        Some(wait) => limiter.acquire_waiting(liters, wait).await,
+        None => limiter.try_acquire(liters).await,
+    }
+}
+
+pub(super) fn invalid_wait(e: &InvalidWait) -> super::Response {
+    tracing::info!(err = %e, "invalid wait");
+    super::Response::builder()
+        .status(http::StatusCode::BAD_REQUEST)
+        .header(http::header::CONTENT_TYPE, "plain/text")
+        .body(hyper::Body::from(format!("{e}\n")))
+        .unwrap()
+}
+
+// MARK: withdraw
+
+/// `{"withdraw": {"pints": 2}, "policy": "available"}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WithdrawRequest {
+    withdraw: Quantity,
+    #[serde(default)]
+    policy: FulfilmentPolicy,
+}
+
+/// What to deliver when less than requested is available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FulfilmentPolicy {
+    /// all of the request or nothing
+    #[default]
+    AllOrNothing,
+    /// what is available, failing only when nothing is
+    Available,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("invalid amount {0}, expected at least one millilitre")]
+pub struct InvalidWithdraw(f64);
+
+/// [`milk::Pack`] delivered for a [`WithdrawRequest`], in the requested unit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Delivered {
+    withdrawn: Quantity,
+    /// level of the bucket right after the withdrawal
+    remaining: Quantity,
+}
+
+impl WithdrawRequest {
+    /// `None` unless `body` is a JSON object with a `withdraw` field
+    pub(super) fn detect(body: &[u8]) -> Option<serde_json::Result<Self>> {
+        let value: Value = serde_json::from_slice(body).ok()?;
+        value.get("withdraw")?;
+        Some(serde_json::from_value(value))
+    }
+
+    pub(super) fn policy(&self) -> FulfilmentPolicy {
+        self.policy
+    }
+
+    /// at least one millilitre, the least a bucket withdraws
+    pub(super) fn liters(&self) -> Result<Liters, InvalidWithdraw> {
+        let value = self.withdraw.value();
+        let liters = self.withdraw.unit().to_liters(value);
+        if !liters.is_finite() || liters < MILLILITERS.to_liters(1.0) {
+            return Err(InvalidWithdraw(value));
+        }
+        Ok(Liters(liters as f32))
+    }
+
+    pub(super) fn delivered(&self, pack: &milk::Pack) -> Delivered {
+        let unit = self.withdraw.unit();
+        let withdrawn = if self.liters().is_ok_and(|l| l == pack.inner()) {
+            // as requested, without a round trip through liters
+            self.withdraw.value()
+        } else {
+            unit.from_liters(widen(pack.inner().0))
+        };
+        let remaining = unit.from_liters(widen(pack.remaining().0));
+        Delivered {
+            withdrawn: self.withdraw.with_value(withdrawn),
+            remaining: self.withdraw.with_value(remaining),
+        }
+    }
+}
+
+/// [`withdraw`], then what is available for [`FulfilmentPolicy::Available`]
+pub(super) async fn withdraw_by_policy(
+    limiter: &dyn RateLimiter,
+    liters: Liters,
+    wait: Option<std::time::Duration>,
+    policy: FulfilmentPolicy,
+) -> Result<milk::Pack, milk::Empty> {
+    match (policy, wait) {
+        (FulfilmentPolicy::AllOrNothing, _) => withdraw(limiter, liters, wait).await,
+        (FulfilmentPolicy::Available, None) => limiter.try_acquire_up_to(liters).await,
+        (FulfilmentPolicy::Available, Some(wait)) => {
+            // wait for all of it, and take what is there when the time is up
+            match limiter.acquire_waiting(liters, wait).await {
+                Ok(pack) => Ok(pack),
+                Err(_) => limiter.try_acquire_up_to(liters).await,
+            }
+        }
+    }
+}
+
+pub(super) fn invalid_withdraw(e: &InvalidWithdraw) -> super::Response {
+    tracing::info!(err = %e, "invalid withdrawal");
+    super::Response::builder()
+        .status(http::StatusCode::BAD_REQUEST)
+        .header(http::header::CONTENT_TYPE, "plain/text")
+        .body(hyper::Body::from(format!("{e}\n")))
+        .unwrap()
+}
+
+// MARK: stream
+
+/// interval of `: heartbeat` comments on `/9/stream`
+pub(super) const STREAM_HEARTBEAT: std::time::Duration = std::time::Duration::from_secs(15);
+
+pub(super) fn sse_event(event: milk::LevelEvent) -> warp::sse::Event {
+    let kind = match event.kind() {
+        milk::EventKind::Level => "level",
+        milk::EventKind::Withdraw => "withdraw",
+        milk::EventKind::Refill => "refill",
+        milk::EventKind::Fulfill => "fulfill",
+    };
+    warp::sse::Event::default()
+        .id(event.id().to_string())
+        .event(kind)
+        .json_data(event)
+        .unwrap()
+}
+
+fn ceil_secs(duration: std::time::Duration) -> u64 {
+    duration.as_secs_f64().ceil() as u64
+}
+
+/// `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers in whole liters and seconds
+pub fn rate_limit_headers(
+    builder: http::response::Builder,
+    quota: &milk::Quota,
+) -> http::response::Builder {
+    let builder = builder
+        .header("ratelimit-limit", quota.limit().0.floor().max(0.0) as u64)
+        .header(
+            "ratelimit-remaining",
+            quota.remaining().0.floor().max(0.0) as u64,
+        );
+    match quota.reset() {
+        Some(reset) => builder.header("ratelimit-reset", ceil_secs(reset)),
+        None => builder,
+    }
+}
+
+pub fn empty_bucket(empty: &milk::Empty) -> super::Response {
+    tracing::error!(available = ?empty.available(), "rate limit reached");
+    let body = hyper::Body::from(format!("{empty}\n"));
+    let builder = super::Response::builder()
+        .status(http::StatusCode::TOO_MANY_REQUESTS)
+        .header(http::header::CONTENT_TYPE, "plain/text");
+    let builder = rate_limit_headers(builder, empty.quota());
+    let builder = match empty.retry_after() {
+        Some(after) => builder.header(http::header::RETRY_AFTER, ceil_secs(after)),
+        None => builder,
+    };
+    builder.body(body).unwrap()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
@@ -69,6 +512,15 @@ pub enum Error {
     JsonError(#[from] serde_json::Error),
 }
 
+/// 400 for a body which could not be converted after the milk was withdrawn
+pub(super) fn invalid_conversion(e: &Error, quota: &milk::Quota) -> super::Response {
+    tracing::info!(err = e as &dyn std::error::Error, "invalid conversion");
+    rate_limit_headers(super::Response::builder(), quota)
+        .status(http::StatusCode::BAD_REQUEST)
+        .body(hyper::Body::empty())
+        .unwrap()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
@@ -105,6 +557,21 @@ impl From<Pints> for Unit {
     }
 }
 
+impl TryFrom<&Quantity> for Unit {
+    type Error = InvalidQuantity;
+
+    fn try_from(value: &Quantity) -> Result<Self, Self::Error> {
+        let v = value.value() as f32;
+        match value.name() {
+            "liters" => Ok(Self::Liters(v)),
+            "gallons" => Ok(Self::Gallons(v)),
+            "litres" => Ok(Self::Litres(v)),
+            "pints" => Ok(Self::Pints(v)),
+            name => Err(InvalidQuantity::NoPartner(name.to_string())),
+        }
+    }
+}
+
 impl Unit {
     pub(super) fn convert(self) -> Self {
         match self {
@@ -114,4 +581,119 @@ impl Unit {
             Self::Pints(p) => Pints(p).litres().into(),
         }
     }
+
+    fn value(self) -> f32 {
+        match self {
+            Self::Liters(v) | Self::Gallons(v) | Self::Litres(v) | Self::Pints(v) => v,
+        }
+    }
+
+    fn partner(self) -> &'static VolumeUnit {
+        match self {
+            Self::Liters(_) => Gallons::UNIT,
+            Self::Gallons(_) => Liters::UNIT,
+            Self::Litres(_) => Pints::UNIT,
+            Self::Pints(_) => Litres::UNIT,
+        }
+    }
+}
+
+/// `{"from": {"cups": 2}, "to": "ml"}`, or a [`Unit`] converted to its partner
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ConvertRequest {
+    To(ConvertTo),
+    /// parsed in `f64` for exact conversions
+    Partner(Quantity),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConvertTo {
+    from: Quantity,
+    to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Converted {
+    To(Quantity),
+    Partner(Unit),
+}
+
+impl ConvertRequest {
+    pub(super) fn parse(body: &[u8]) -> Result<Self, Error> {
+        let body = std::str::from_utf8(body)?;
+        let request = serde_json::from_str(body)?;
+        Ok(request)
+    }
+
+    pub(super) fn convert(self, exact: Option<Exact>) -> Result<Converted, InvalidQuantity> {
+        let from = match self {
+            Self::To(ConvertTo { from, to }) => {
+                return from.convert_to(&to, exact).map(Converted::To)
+            }
+            Self::Partner(from) => from,
+        };
+        let unit = Unit::try_from(&from)?;
+        match exact {
+            Some(_) => from
+                .convert_to(unit.partner().name(), exact)
+                .map(Converted::To),
+            // in `f32` as ever
+            None => match unit.convert() {
+                converted if converted.value().is_finite() => Ok(Converted::Partner(converted)),
+                _ => Err(InvalidQuantity::Overflow(from.name().to_string())),
+            },
+        }
+    }
+}
+
+/// `?precision=3&rounding=half_up` for an exact conversion, otherwise `f64` arithmetic
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConvertQuery {
+    precision: Option<String>,
+    rounding: Option<String>,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum InvalidExact {
+    #[error("invalid precision {0:?}, expected decimal places up to {max}", max = Exact::MAX_PRECISION)]
+    Precision(String),
+    #[error(transparent)]
+    Rounding(#[from] InvalidRounding),
+    #[error("rounding is only for exact conversions, which need a precision")]
+    RoundingWithoutPrecision,
+}
+
+impl ConvertQuery {
+    /// `None` unless a precision is requested, rounding half to even by default
+    pub(super) fn exact(&self) -> Result<Option<Exact>, InvalidExact> {
+        let Some(precision) = self.precision.as_deref() else {
+            return match self.rounding {
+                Some(_) => Err(InvalidExact::RoundingWithoutPrecision),
+                None => Ok(None),
+            };
+        };
+        let rounding = match self.rounding.as_deref() {
+            Some(r) => r.parse()?,
+            None => Rounding::default(),
+        };
+        let exact = precision
+            .trim()
+            .parse()
+            .ok()
+            .and_then(|p| Exact::new(p, rounding))
+            .ok_or_else(|| InvalidExact::Precision(precision.to_string()))?;
+        Ok(Some(exact))
+    }
+}
+
+pub(super) fn invalid_exact(e: &InvalidExact) -> super::Response {
+    tracing::info!(err = %e, "invalid exact conversion");
+    super::Response::builder()
+        .status(http::StatusCode::BAD_REQUEST)
+        .header(http::header::CONTENT_TYPE, "plain/text")
+        .body(hyper::Body::from(format!("{e}\n")))
+        .unwrap()
 }
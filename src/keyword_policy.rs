@@ -0,0 +1,204 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use cargo_manifest::Manifest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// at least one of the keywords
+    #[default]
+    Any,
+    /// every keyword
+    All,
+}
+
+/// Where to look for keywords in a manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    /// `package.keywords`
+    Keywords,
+    /// `package.categories`
+    Categories,
+    /// `package.metadata.keywords`
+    Metadata,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keywords => f.write_str("keywords"),
+            Self::Categories => f.write_str("categories"),
+            Self::Metadata => f.write_str("metadata.keywords"),
+        }
+    }
+}
+
+impl Field {
+    fn defaults() -> Vec<Self> {
+        vec![Self::Keywords]
+    }
+
+    fn values(self, manifest: &Manifest) -> Vec<&str> {
+        let Some(package) = manifest.package.as_ref() else {
+            return vec![];
+        };
+        let list = match self {
+            Self::Keywords => package.keywords.as_ref(),
+            Self::Categories => package.categories.as_ref(),
+            Self::Metadata => {
+                let keywords = package
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.get("keywords"))
+                    .and_then(toml::Value::as_array);
+                return keywords
+                    .into_iter()
+                    .flatten()
+                    .filter_map(toml::Value::as_str)
+                    .collect();
+            }
+        };
+        let Some(list) = list else {
+            return vec![];
+        };
+        let Some(list) = list.as_ref().as_local() else {
+            tracing::info!(field = %self, "inherited from an unresolved workspace");
+            return vec![];
+        };
+        list.iter().map(String::as_str).collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    #[error("could not parse keyword policy")]
+    Toml(#[from] toml::de::Error),
+    #[error("no keywords given")]
+    NoKeywords,
+    #[error("invalid glob pattern {pattern:?}")]
+    Pattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+}
+
+/// Keywords a manifest has to carry
+///
+/// ```toml
+/// keywords = ["Christmas 2024", "xmas-*"]
+/// mode = "any"
+/// case_insensitive = true
+/// glob = true
+/// fields = ["keywords", "categories", "metadata"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct KeywordPolicy {
+    keywords: Vec<String>,
+    #[serde(default)]
+    mode: MatchMode,
+    #[serde(default)]
+    case_insensitive: bool,
+    /// treat `keywords` as glob patterns
+    #[serde(default)]
+    glob: bool,
+    #[serde(default = "Field::defaults")]
+    fields: Vec<Field>,
+    /// `keywords` compiled by [`Self::from_toml`] when `glob` is set
+    #[serde(skip)]
+    patterns: Vec<glob::Pattern>,
+}
+
+/// The rule which a manifest did not satisfy
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum PolicyViolation {
+    #[error("none of {keywords:?} found in {fields}")]
+    NoneOf {
+        keywords: Vec<String>,
+        fields: String,
+    },
+    #[error("{keyword:?} not found in {fields}")]
+    Missing { keyword: String, fields: String },
+}
+
+impl KeywordPolicy {
+    /// The policy of a single, exactly matching keyword in `package.keywords`
+    pub fn exact(keyword: String) -> Self {
+        Self {
+            keywords: vec![keyword],
+            mode: MatchMode::Any,
+            case_insensitive: false,
+            glob: false,
+            fields: Field::defaults(),
+            patterns: vec![],
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, PolicyError> {
+        let mut policy: Self = toml::from_str(source)?;
+        if policy.keywords.is_empty() {
+            return Err(PolicyError::NoKeywords);
+        }
+        if policy.glob {
+            policy.patterns = policy
+                .keywords
+                .iter()
+                .map(|pattern| {
+                    glob::Pattern::new(pattern).map_err(|source| PolicyError::Pattern {
+                        pattern: pattern.clone(),
+                        source,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(policy)
+    }
+
+    /// whether `value` satisfies the `index`th keyword
+    fn matches(&self, index: usize, value: &str) -> bool {
+        if let Some(pattern) = self.patterns.get(index) {
+            let options = glob::MatchOptions {
+                case_sensitive: !self.case_insensitive,
+                ..Default::default()
+            };
+            return pattern.matches_with(value, options);
+        }
+        let keyword = &self.keywords[index];
+        if self.case_insensitive {
+            keyword.to_lowercase() == value.to_lowercase()
+        } else {
+            keyword == value
+        }
+    }
+
+    fn fields(&self) -> String {
+        let fields: Vec<_> = self.fields.iter().map(Field::to_string).collect();
+        fields.join(", ")
+    }
+
+    pub fn check(&self, manifest: &Manifest) -> Result<(), PolicyViolation> {
+        let values: Vec<_> = self
+            .fields
+            .iter()
+            .flat_map(|f| f.values(manifest))
+            .collect();
+        let found = |index: &usize| values.iter().any(|v| self.matches(*index, v));
+        let mut indices = 0..self.keywords.len();
+        match self.mode {
+            MatchMode::Any if indices.any(|i| found(&i)) => Ok(()),
+            MatchMode::Any => Err(PolicyViolation::NoneOf {
+                keywords: self.keywords.clone(),
+                fields: self.fields(),
+            }),
+            MatchMode::All => match indices.find(|i| !found(i)) {
+                None => Ok(()),
+                Some(index) => Err(PolicyViolation::Missing {
+                    keyword: self.keywords[index].clone(),
+                    fields: self.fields(),
+                }),
+            },
+        }
+    }
+}
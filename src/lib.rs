@@ -1,9 +1,13 @@
 pub mod bucket;
+pub mod catalog;
 pub mod connect4;
 pub mod cookie;
 pub mod handlers;
 pub mod jwt;
+pub mod keyword_policy;
 pub mod quotes;
 pub mod routes;
+pub mod snapshots;
+pub mod submissions;
 
 pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!();
@@ -1,15 +1,19 @@
+use std::convert::Infallible;
 use std::error::Error as StdError;
 use std::str::FromStr;
 use std::sync::Arc;
 
 use warp::{http, hyper, Filter, Reply};
 
+use crate::connect4::GameId;
 use crate::handlers;
 
 mod json;
+mod multipart;
 mod reject;
 mod state;
 mod toml;
+mod yaml;
 
 use self::reject::InvalidBodyEncoding;
 
@@ -31,8 +35,11 @@ pub fn make(state: State) -> impl Filter<Extract = (impl Reply,), Error = warp::
         .or(ipv6_dest(state.clone()))
         .or(ipv6_key(state.clone()))
         .or(manifest_order(state.clone()))
-        .or(milk_factory(state.clone()))
-        .or(refill_milk(state.clone()))
+        .or(submissions(state.clone()))
+        .or(manifest_diff(state.clone()))
+        .or(lockfile_report())
+        .or(milk(state.clone()))
+        .or(connect4_games(state.clone()))
         .or(connect4_board(state.clone()))
         .or(connect4_reset(state.clone()))
         .or(connect4_place(state.clone()))
@@ -116,16 +123,79 @@ fn ipv6_key(
         .and_then(handlers::ipv6_key)
 }
 
-fn manifest_order(
-    state: State,
-) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+/// `content-type` of the media type `essence`, whatever its parameters such as `charset`
+fn media_type(essence: &'static str) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::<mime::Mime>("content-type")
+        .and_then(move |media_type: mime::Mime| async move {
+            if media_type.essence_str() == essence {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+}
+
+/// Boxed like [`milk`], as the verbose report makes it deep enough to overflow the stack
+fn manifest_order(state: State) -> warp::filters::BoxedFilter<(warp::reply::Response,)> {
+    use handlers::manifest::{Manifest, WorkspaceRoot};
+
+    // member Cargo.toml with its workspace root manifest
+    let workspace_form = multipart::form().and_then(|mut parts: multipart::Parts| async move {
+        let member: Manifest = self::toml::from_bytes(&parts.take("manifest")?)?;
+        let root: WorkspaceRoot = self::toml::from_bytes(&parts.take("workspace")?)?;
+        let manifest = handlers::manifest::inherit_workspace(member, &root);
+        Ok::<_, warp::Rejection>(manifest)
+    });
+    let manifest = self::toml::toml_body::<Manifest>()
+        .or(json::json_body::<Manifest>())
+        .unify()
+        .or(yaml::yaml_body::<Manifest>())
+        .unify()
+        .or(workspace_form)
+        .unify();
+    let verbose = warp::query::<handlers::manifest::ReportQuery>()
+        .and_then(|q: handlers::manifest::ReportQuery| async move {
+            if q.is_verbose() {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one();
+    let s = state.clone();
+    let report_form = multipart::form()
+        .map(move |mut parts: multipart::Parts| {
+            let member = parts.take("manifest").ok();
+            let root = parts.take("workspace").ok();
+            (Arc::clone(&s.manifest), member, root)
+        })
+        .untuple_one()
+        .and_then(handlers::manifest_workspace_report);
+    let s = state.clone();
+    let report_raw = warp::any()
+        .map(move || Arc::clone(&s.manifest))
+        .and(warp::header::<String>("content-type"))
+        .and(warp::body::bytes())
+        .and_then(handlers::manifest_report);
+    let report = verbose.and(Filter::or(report_form, report_raw));
+    let order = warp::any()
+        .map(move || Arc::clone(&state.manifest))
+        .and(manifest)
+        .and(warp::query::<handlers::manifest::OrderQuery>())
+        .and_then(handlers::manifest_order);
     warp::path!("5" / "manifest")
         .and(warp::post())
-        .map(move || Arc::clone(&state.manifest))
-        .and(self::toml::toml_body())
-        .and_then(handlers::manifest_order)
+        .and(Filter::or(report, order))
         .recover(|r: warp::Rejection| async move {
+            use self::json::RejectJson;
+            use self::multipart::RejectMultipart;
             use self::toml::RejectToml;
+            use self::yaml::RejectYaml;
+            if let Some(e) = r.find::<RejectMultipart>() {
+                let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
+                return Ok(reply);
+            }
             if let Some(e) = r.find::<InvalidBodyEncoding>() {
                 let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
                 return Ok(reply);
@@ -134,8 +204,142 @@ fn manifest_order(
                 let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
                 return Ok(reply);
             }
+            if let Some(e) = r.find::<RejectJson>() {
+                let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
+                return Ok(reply);
+            }
+            if let Some(e) = r.find::<RejectYaml>() {
+                let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
+                return Ok(reply);
+            }
             Err(r)
         })
+        .map(Reply::into_response)
+        .boxed()
+}
+
+fn manifest_diff(
+    _state: State,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    use handlers::manifest::{inherit_workspace, Manifest, WorkspaceRoot};
+
+    // two revisions of Cargo.toml, optionally with their workspace root manifest
+    let revisions = multipart::form().and_then(|mut parts: multipart::Parts| async move {
+        let old: Manifest = self::toml::from_bytes(&parts.take("old")?)?;
+        let new: Manifest = self::toml::from_bytes(&parts.take("new")?)?;
+        let Ok(root) = parts.take("workspace") else {
+            return Ok::<_, warp::Rejection>((old, new));
+        };
+        let root: WorkspaceRoot = self::toml::from_bytes(&root)?;
+        Ok((inherit_workspace(old, &root), inherit_workspace(new, &root)))
+    });
+    warp::path!("5" / "manifest" / "diff")
+        .and(warp::post())
+        .and(revisions)
+        .untuple_one()
+        .and_then(handlers::manifest_diff)
+        .recover(|r: warp::Rejection| async move {
+            use self::multipart::RejectMultipart;
+            use self::toml::RejectToml;
+            if let Some(e) = r.find::<RejectMultipart>() {
+                let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
+                return Ok(reply);
+            }
+            if let Some(e) = r.find::<InvalidBodyEncoding>() {
+                let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
+                return Ok(reply);
+            }
+            if let Some(e) = r.find::<RejectToml>() {
+                let reply = e.recover_with(|_| "Invalid manifest".to_string()).await;
+                return Ok(reply);
+            }
+            Err(r)
+        })
+}
+
+fn lockfile_report() -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    use handlers::lockfile::{Lockfile, ReportQuery};
+
+    warp::path!("5" / "lockfile")
+        .and(warp::post())
+        .and(self::toml::body::<Lockfile>())
+        .and(warp::query::<ReportQuery>())
+        .and_then(handlers::lockfile_report)
+        .recover(|r: warp::Rejection| async move {
+            use self::toml::RejectToml;
+            if let Some(e) = r.find::<InvalidBodyEncoding>() {
+                let reply = e.recover_with(|_| "Invalid lockfile".to_string()).await;
+                return Ok(reply);
+            }
+            if let Some(e) = r.find::<RejectToml>() {
+                let reply = e.recover_with(|_| "Invalid lockfile".to_string()).await;
+                return Ok(reply);
+            }
+            Err(r)
+        })
+}
+
+fn submissions(
+    state: State,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    let State { manifest, .. } = state;
+    let use_state = warp::any().map(move || Arc::clone(&manifest));
+    let list = warp::path!("5" / "submissions")
+        .and(warp::get())
+        .and(use_state.clone())
+        .and(warp::query::<handlers::manifest::SubmissionListQuery>())
+        .and_then(handlers::submissions_list);
+    let totals = warp::path!("5" / "submissions" / "totals")
+        .and(warp::get())
+        .and(use_state.clone())
+        .and(warp::query::<handlers::manifest::TotalsQuery>())
+        .and_then(handlers::submissions_totals);
+    let get = warp::path!("5" / "submissions" / String)
+        .and(warp::get())
+        .map(|id: String| id.parse().map(handlers::manifest::SubmissionPathParam::new))
+        .and(use_state.clone())
+        .and_then(|param, state| async move {
+            error_bad_request!(
+                param;
+                Ok(p) => handlers::submissions_get(state, p).await
+            )
+        });
+    list.or(totals).or(get)
+}
+
+/// Day 9 endpoints, boxed to keep the futures of [`make`] from overflowing the stack
+fn milk(state: State) -> warp::filters::BoxedFilter<(warp::reply::Response,)> {
+    milk_factory(state.clone())
+        .or(refill_milk(state.clone()))
+        .or(milk_buckets(state.clone()))
+        .or(milk_rate(state.clone()))
+        .or(milk_stream(state))
+        .map(Reply::into_response)
+        .boxed()
+}
+
+/// [`ClientKey`](crate::bucket::clients::ClientKey) of the caller
+fn milk_client(
+    state: State,
+) -> impl Filter<Extract = (crate::bucket::clients::ClientKey,), Error = Infallible> + Clone {
+    let State {
+        milk, auth_token, ..
+    } = state;
+    warp::addr::remote()
+        .and(warp::header::headers_cloned())
+        .then(move |remote, headers: http::HeaderMap| {
+            let milk = Arc::clone(&milk);
+            let auth_token = Arc::clone(&auth_token);
+            async move { handlers::milk::client_key(&milk, &auth_token, remote, &headers).await }
+        })
+}
+
+/// `?wait=` or `Prefer: wait=` of the caller
+fn milk_wait(
+) -> impl Filter<Extract = (handlers::milk::WaitRequest,), Error = warp::Rejection> + Clone {
+    warp::query::<handlers::milk::WaitQuery>()
+        .and(warp::header::optional::<String>("prefer"))
+        .map(handlers::milk::WaitQuery::with_prefer)
 }
 
 fn milk_factory(
@@ -144,11 +348,14 @@ fn milk_factory(
     let s = state.clone();
     let convert_unit = warp::any()
         .map(move || Arc::clone(&s.milk))
+        .and(milk_client(state.clone()))
+        .and(milk_wait())
+        .and(warp::query::<handlers::milk::ConvertQuery>())
         .and(json::header())
         .and(warp::body::bytes())
-        .and_then(|m, b| async move {
+        .and_then(|m, c, w, q, b| async move {
             use handlers::milk::Error;
-            match handlers::convert_milk_unit(m, b).await {
+            match handlers::convert_milk_unit(m, c, w, q, b).await {
                 Ok(res) => Ok(res),
                 Err(Error::Utf8Error(e)) => Err(InvalidBodyEncoding::wrap_into_reject(e)),
                 Err(Error::JsonError(e)) => Err(json::RejectJson::wrap_into_reject(e)),
@@ -158,6 +365,8 @@ fn milk_factory(
     let s = state.clone();
     let request_milk = warp::any()
         .map(move || Arc::clone(&s.milk))
+        .and(milk_client(state.clone()))
+        .and(milk_wait())
         .and_then(handlers::request_milk);
     warp::path!("9" / "milk")
         .and(warp::post())
@@ -167,30 +376,106 @@ fn milk_factory(
 fn refill_milk(
     state: State,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    let client = milk_client(state.clone());
     warp::path!("9" / "refill")
         .and(warp::post())
         .map(move || Arc::clone(&state.milk))
+        .and(client)
         .and_then(handlers::refill_milk)
 }
 
+fn milk_buckets(
+    state: State,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("9" / "buckets")
+        .and(warp::get())
+        .map(move || Arc::clone(&state.milk))
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(handlers::milk_buckets)
+}
+
+fn milk_stream(
+    state: State,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    let client = milk_client(state.clone());
+    warp::path!("9" / "stream")
+        .and(warp::get())
+        .map(move || Arc::clone(&state.milk))
+        .and(client)
+        .and(warp::sse::last_event_id::<u64>())
+        .and_then(handlers::milk_stream)
+}
+
+fn milk_rate(
+    state: State,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    let s = state.clone();
+    let get = warp::get()
+        .map(move || Arc::clone(&s.milk))
+        .and_then(handlers::milk_rate);
+    let put = warp::put()
+        .map(move || Arc::clone(&state.milk))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(json::json_body())
+        .and_then(handlers::set_milk_rate)
+        .recover(json::recover);
+    warp::path!("9" / "rate").and(get.or(put))
+}
+
+fn connect4_games(
+    state: State,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    let State { connect4, .. } = state;
+    let connect4 = warp::any().map(move || Arc::clone(&connect4));
+    let create = warp::path!("12" / "games")
+        .and(warp::post())
+        .and(connect4.clone())
+        .and(warp::body::bytes())
+        .and_then(handlers::connect4_create);
+    let list = warp::path!("12" / "games")
+        .and(warp::get())
+        .and(connect4.clone())
+        .and_then(handlers::connect4_list);
+    let delete = warp::path!("12" / "games" / GameId)
+        .and(warp::delete())
+        .and(connect4)
+        .and_then(|id, connect4| handlers::connect4_delete(connect4, id));
+    create.or(list).or(delete)
+}
+
+/// `/12/{op}` on the default game or `/12/games/{id}/{op}`
+fn connect4_game(
+    op: &'static str,
+) -> impl Filter<Extract = (GameId,), Error = warp::Rejection> + Clone {
+    let legacy = warp::path("12")
+        .and(warp::path(op))
+        .and(warp::path::end())
+        .map(GameId::default);
+    let game = warp::path("12")
+        .and(warp::path("games"))
+        .and(warp::path::param::<GameId>())
+        .and(warp::path(op))
+        .and(warp::path::end());
+    legacy.or(game).unify()
+}
+
 fn connect4_board(
     state: State,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     let State { connect4, .. } = state;
-    warp::path!("12" / "board")
+    connect4_game("board")
         .and(warp::get())
-        .map(move || Arc::clone(&connect4))
-        .and_then(handlers::connect4_board)
+        .and_then(move |id| handlers::connect4_board(Arc::clone(&connect4), id))
 }
 
 fn connect4_reset(
     state: State,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     let State { connect4, .. } = state;
-    warp::path!("12" / "reset")
+    connect4_game("reset")
         .and(warp::post())
-        .map(move || Arc::clone(&connect4))
-        .and_then(handlers::connect4_reset)
+        .and(warp::body::bytes())
+        .and_then(move |id, body| handlers::connect4_reset(Arc::clone(&connect4), id, body))
 }
 
 fn connect4_place(
@@ -228,16 +513,23 @@ fn connect4_place(
     }
 
     let State { connect4, .. } = state;
-    warp::path!("12" / "place" / String / String)
-        .map(move |t: String, c: String| {
+    let legacy = warp::path!("12" / "place" / String / String).map(|t, c| (GameId::DEFAULT, t, c));
+    let game =
+        warp::path!("12" / "games" / GameId / "place" / String / String).map(|id, t, c| (id, t, c));
+    legacy
+        .or(game)
+        .unify()
+        .untuple_one()
+        .map(move |id: GameId, t: String, c: String| {
             (
                 Arc::clone(&connect4),
+                id,
                 Team::from_str(&t),
                 usize::from_str(&c),
             )
         })
         .untuple_one()
-        .and_then(|connect4, t, c| async move {
+        .and_then(|connect4, id, t, c| async move {
             let (team, col) = match (t, c) {
                 (Ok(t), Ok(c)) => (crate::connect4::Team::from(t), usize::wrapping_sub(c, 1)),
                 e => {
@@ -250,7 +542,7 @@ fn connect4_place(
                 }
             };
             let param = handlers::connect4::PlacePathParam::new(team, col);
-            handlers::connect4_place(connect4, param).await
+            handlers::connect4_place(connect4, id, param).await
         })
 }
 
@@ -258,10 +550,9 @@ fn connect4_random_board(
     state: State,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     let State { connect4, .. } = state;
-    warp::path!("12" / "random-board")
+    connect4_game("random-board")
         .and(warp::get())
-        .map(move || Arc::clone(&connect4))
-        .and_then(handlers::connect4_random_board)
+        .and_then(move |id| handlers::connect4_random_board(Arc::clone(&connect4), id))
 }
 
 fn jwt_wrap(state: State) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
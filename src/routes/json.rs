@@ -41,7 +41,7 @@ impl RejectJson {
 }
 
 pub fn header() -> impl Filter<Extract = (), Error = Rejection> + Clone {
-    warp::filters::header::exact("content-type", "application/json")
+    super::media_type("application/json")
 }
 
 pub fn body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
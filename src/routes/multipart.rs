@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_util::TryStreamExt;
+use warp::{http, hyper, multipart, reject, Filter, Rejection};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RejectMultipart {
+    #[error("could not read request body as multipart form")]
+    Form(#[from] warp::Error),
+    #[error("multipart form has no part named {0:?}")]
+    MissingPart(String),
+}
+
+impl reject::Reject for RejectMultipart {}
+
+impl RejectMultipart {
+    pub fn wrap_into_reject(source: warp::Error) -> Rejection {
+        reject::custom(Self::from(source))
+    }
+
+    pub fn missing_part(name: &str) -> Rejection {
+        reject::custom(Self::MissingPart(name.to_string()))
+    }
+
+    pub async fn recover_with<F>(&self, message: F) -> http::Response<hyper::Body>
+    where
+        F: FnOnce(&Self) -> String,
+    {
+        {
+            let error = self as &(dyn std::error::Error);
+            tracing::error!(error);
+        }
+        let message = message(self);
+        http::Response::builder()
+            .status(http::StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from(message))
+            .unwrap()
+    }
+}
+
+/// Contents of a multipart form, keyed by part name
+#[derive(Debug, Clone, Default)]
+pub struct Parts(HashMap<String, Bytes>);
+
+impl Parts {
+    pub fn take(&mut self, name: &str) -> Result<Bytes, Rejection> {
+        self.0
+            .remove(name)
+            .ok_or_else(|| RejectMultipart::missing_part(name))
+    }
+}
+
+const MAX_LENGTH: u64 = 1024 * 64;
+
+pub fn form() -> impl Filter<Extract = (Parts,), Error = Rejection> + Clone {
+    multipart::form()
+        .max_length(MAX_LENGTH)
+        .and_then(collect_parts)
+}
+
+async fn collect_parts(form: multipart::FormData) -> Result<Parts, Rejection> {
+    let parts = form
+        .and_then(|part| async move {
+            let name = part.name().to_string();
+            let data = part
+                .stream()
+                .try_fold(BytesMut::new(), |mut buf, chunk| async move {
+                    buf.put(chunk);
+                    Ok(buf)
+                })
+                .await?;
+            Ok((name, data.freeze()))
+        })
+        .try_collect()
+        .await
+        .map_err(RejectMultipart::wrap_into_reject)?;
+    Ok(Parts(parts))
+}
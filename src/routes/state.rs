@@ -1,24 +1,36 @@
 use std::{borrow::Cow, future::Future, sync::Arc};
 
-use crate::{bucket, cookie, handlers::auth_token, jwt, quotes};
+use crate::{
+    bucket, catalog, connect4, cookie, handlers::auth_token, jwt, keyword_policy, quotes,
+    submissions,
+};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Builder<
     SeekUrl = (),
-    ManifestKeyword = (),
-    MilkBucket = (),
+    KeywordPolicy = (),
+    MilkLimiter = (),
+    MilkClients = (),
     JwtManager = (),
     CookieManager = (),
     JwtDecoder = (),
     QuotesRepository = (),
+    SubmissionsRepository = (),
+    CatalogRepository = (),
+    AdminToken = (),
 > {
     seek_url: SeekUrl,
-    manifest_keyword: ManifestKeyword,
-    milk_bucket: MilkBucket,
+    keyword_policy: KeywordPolicy,
+    milk_limiter: MilkLimiter,
+    milk_clients: MilkClients,
     jwt_manager: JwtManager,
     cookie_manager: CookieManager,
     jwt_decoder: JwtDecoder,
     quotes_repo: QuotesRepository,
+    submissions_repo: SubmissionsRepository,
+    catalog_repo: CatalogRepository,
+    admin_token: AdminToken,
+    connect4_games: connect4::games::Builder,
 }
 
 impl Builder {
@@ -27,23 +39,32 @@ impl Builder {
     }
 }
 
+#[allow(clippy::type_complexity)]
 impl<
         SeekUrl,
-        ManifestKeyword,
-        MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
         JwtManager,
         CookieManager,
         JwtDecoder,
         QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     >
     Builder<
         SeekUrl,
-        ManifestKeyword,
-        MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
         JwtManager,
         CookieManager,
         JwtDecoder,
         QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     >
 {
     pub fn seek_url<'s, S>(
@@ -51,94 +72,210 @@ impl<
         value: S,
     ) -> Builder<
         String,
-        ManifestKeyword,
-        MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
         JwtManager,
         CookieManager,
         JwtDecoder,
         QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     >
     where
         S: Into<Cow<'s, str>>,
     {
         let Self {
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
             ..
         } = self;
         let seek_url = value.into().into_owned();
         Builder {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
         }
     }
 
+    pub fn keyword_policy(
+        self,
+        value: keyword_policy::KeywordPolicy,
+    ) -> Builder<
+        SeekUrl,
+        keyword_policy::KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
+        JwtManager,
+        CookieManager,
+        JwtDecoder,
+        QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
+    > {
+        let Self {
+            seek_url,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
+            ..
+        } = self;
+        Builder {
+            seek_url,
+            keyword_policy: value,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
+        }
+    }
+
+    /// Shorthand of [`keyword_policy::KeywordPolicy::exact`]
     pub fn manifest_keyword<'s, S>(
         self,
         value: S,
-    ) -> Builder<SeekUrl, String, MilkBucket, JwtManager, CookieManager, JwtDecoder, QuotesRepository>
+    ) -> Builder<
+        SeekUrl,
+        keyword_policy::KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
+        JwtManager,
+        CookieManager,
+        JwtDecoder,
+        QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
+    >
     where
         S: Into<Cow<'s, str>>,
     {
+        let policy = keyword_policy::KeywordPolicy::exact(value.into().into_owned());
+        self.keyword_policy(policy)
+    }
+
+    pub fn milk_limiter(
+        self,
+        value: Arc<dyn bucket::limiter::RateLimiter>,
+    ) -> Builder<
+        SeekUrl,
+        KeywordPolicy,
+        Arc<dyn bucket::limiter::RateLimiter>,
+        MilkClients,
+        JwtManager,
+        CookieManager,
+        JwtDecoder,
+        QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
+    > {
         let Self {
             seek_url,
-            milk_bucket,
+            keyword_policy,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
             ..
         } = self;
-        let manifest_keyword = value.into().into_owned();
         Builder {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter: value,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
         }
     }
 
-    pub fn milk_bucket(
+    pub fn milk_clients(
         self,
-        value: bucket::MilkBucket,
+        value: bucket::ClientBuckets,
     ) -> Builder<
         SeekUrl,
-        ManifestKeyword,
-        bucket::MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        bucket::ClientBuckets,
         JwtManager,
         CookieManager,
         JwtDecoder,
         QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     > {
         let Self {
             seek_url,
-            manifest_keyword,
+            keyword_policy,
+            milk_limiter,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
             ..
         } = self;
         Builder {
             seek_url,
-            manifest_keyword,
-            milk_bucket: value,
+            keyword_policy,
+            milk_limiter,
+            milk_clients: value,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
         }
     }
 
@@ -147,30 +284,44 @@ impl<
         value: jwt::Manager,
     ) -> Builder<
         SeekUrl,
-        ManifestKeyword,
-        MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
         jwt::Manager,
         CookieManager,
         JwtDecoder,
         QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     > {
         let Self {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
             ..
         } = self;
         Builder {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager: value,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
         }
     }
 
@@ -179,30 +330,44 @@ impl<
         value: cookie::Manager,
     ) -> Builder<
         SeekUrl,
-        ManifestKeyword,
-        MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
         JwtManager,
         cookie::Manager,
         JwtDecoder,
         QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     > {
         let Self {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
             ..
         } = self;
         Builder {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager: value,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
         }
     }
 
@@ -211,30 +376,44 @@ impl<
         value: jwt::Decoder,
     ) -> Builder<
         SeekUrl,
-        ManifestKeyword,
-        MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
         JwtManager,
         CookieManager,
         jwt::Decoder,
         QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     > {
         let Self {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
             ..
         } = self;
         Builder {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder: value,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
         }
     }
 
@@ -243,30 +422,190 @@ impl<
         value: quotes::Repository,
     ) -> Builder<
         SeekUrl,
-        ManifestKeyword,
-        MilkBucket,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
         JwtManager,
         CookieManager,
         JwtDecoder,
         quotes::Repository,
+        SubmissionsRepository,
+        CatalogRepository,
+        AdminToken,
     > {
         let Self {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
             ..
         } = self;
         Builder {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo: value,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
+        }
+    }
+
+    pub fn submissions_repository(
+        self,
+        value: submissions::Repository,
+    ) -> Builder<
+        SeekUrl,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
+        JwtManager,
+        CookieManager,
+        JwtDecoder,
+        QuotesRepository,
+        submissions::Repository,
+        CatalogRepository,
+        AdminToken,
+    > {
+        let Self {
+            seek_url,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
+            ..
+        } = self;
+        Builder {
+            seek_url,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            submissions_repo: value,
+            catalog_repo,
+            admin_token,
+            connect4_games,
+        }
+    }
+
+    pub fn catalog_repository(
+        self,
+        value: catalog::Repository,
+    ) -> Builder<
+        SeekUrl,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
+        JwtManager,
+        CookieManager,
+        JwtDecoder,
+        QuotesRepository,
+        SubmissionsRepository,
+        catalog::Repository,
+        AdminToken,
+    > {
+        let Self {
+            seek_url,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            submissions_repo,
+            admin_token,
+            connect4_games,
+            ..
+        } = self;
+        Builder {
+            seek_url,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            submissions_repo,
+            catalog_repo: value,
+            admin_token,
+            connect4_games,
+        }
+    }
+
+    pub fn admin_token(
+        self,
+        value: Option<String>,
+    ) -> Builder<
+        SeekUrl,
+        KeywordPolicy,
+        MilkLimiter,
+        MilkClients,
+        JwtManager,
+        CookieManager,
+        JwtDecoder,
+        QuotesRepository,
+        SubmissionsRepository,
+        CatalogRepository,
+        Option<String>,
+    > {
+        let Self {
+            seek_url,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            connect4_games,
+            ..
+        } = self;
+        Builder {
+            seek_url,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
+            jwt_manager,
+            cookie_manager,
+            jwt_decoder,
+            quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token: value,
+            connect4_games,
+        }
+    }
+
+    /// [`connect4::Games`] built by default if not set
+    pub fn connect4_games(self, value: connect4::games::Builder) -> Self {
+        Self {
+            connect4_games: value,
+            ..self
         }
     }
 }
@@ -274,31 +613,46 @@ impl<
 impl
     Builder<
         String,
-        String,
-        bucket::MilkBucket,
+        keyword_policy::KeywordPolicy,
+        Arc<dyn bucket::limiter::RateLimiter>,
+        bucket::ClientBuckets,
         crate::jwt::Manager,
         crate::cookie::Manager,
         crate::jwt::Decoder,
         quotes::Repository,
+        submissions::Repository,
+        catalog::Repository,
+        Option<String>,
     >
 {
     pub fn build(self) -> super::State {
-        use crate::handlers::{manifest, milk, quotes, seek};
+        use crate::handlers::{connect4, manifest, milk, quotes, seek};
 
         let Self {
             seek_url,
-            manifest_keyword,
-            milk_bucket,
+            keyword_policy,
+            milk_limiter,
+            milk_clients,
             jwt_manager,
             cookie_manager,
             jwt_decoder,
             quotes_repo,
+            submissions_repo,
+            catalog_repo,
+            admin_token,
+            connect4_games,
         } = self;
         let seek_state = seek::State::builder().seek_url(seek_url).build();
         let manifest_state = manifest::State::builder()
-            .manifest_keyword(manifest_keyword)
+            .keyword_policy(keyword_policy)
+            .submissions(submissions_repo)
+            .catalog(catalog_repo)
+            .build();
+        let milk = milk::State::builder()
+            .limiter(milk_limiter)
+            .clients(milk_clients)
+            .admin_token(admin_token)
             .build();
-        let milk = milk::State::builder().bucket(milk_bucket).build();
         let auth_token = auth_token::State::builder()
             .jwt_manager(jwt_manager)
             .cookie_manager(cookie_manager)
@@ -309,7 +663,7 @@ impl
             seek: Arc::new(seek_state),
             manifest: Arc::new(manifest_state),
             milk: Arc::new(milk),
-            connect4: Arc::new(Default::default()),
+            connect4: Arc::new(connect4::State::new(connect4_games.build())),
             auth_token: Arc::new(auth_token),
             quotes: Arc::new(quotes),
         }
@@ -322,10 +676,11 @@ impl super::State {
     }
 
     pub fn bg_task(&self) -> impl Future<Output = ()> + Send + 'static {
-        use crate::bucket::{milk, Liters};
-
-        // FIXME: expose configuration
-        let rate = milk::RefillRate::per_sec(Liters(1.0));
-        self.milk.refill_task(rate)
+        let refill = self.milk.refill_task();
+        let evict = self.milk.evict_task();
+        let evict_games = self.connect4.evict_task();
+        async move {
+            futures_util::future::join3(refill, evict, evict_games).await;
+        }
     }
 }
@@ -41,7 +41,7 @@ impl RejectToml {
 }
 
 pub fn header() -> impl Filter<Extract = (), Error = Rejection> + Clone {
-    warp::filters::header::exact("content-type", "application/toml")
+    super::media_type("application/toml")
 }
 
 pub fn body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
@@ -55,7 +55,14 @@ async fn deserialize_toml<T>(body: Bytes) -> Result<T, Rejection>
 where
     T: DeserializeOwned + Send,
 {
-    let s = std::str::from_utf8(&body).map_err(InvalidBodyEncoding::wrap_into_reject)?;
+    from_bytes(&body)
+}
+
+pub fn from_bytes<T>(body: &[u8]) -> Result<T, Rejection>
+where
+    T: DeserializeOwned,
+{
+    let s = std::str::from_utf8(body).map_err(InvalidBodyEncoding::wrap_into_reject)?;
     let t = toml::from_str(s).map_err(RejectToml::wrap_into_reject)?;
     Ok(t)
 }
@@ -0,0 +1,87 @@
+#![allow(dead_code)]
+
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use warp::{http, hyper, reject, reply::Reply, Filter, Rejection};
+
+use super::reject::InvalidBodyEncoding;
+
+#[derive(Debug, thiserror::Error)]
+#[error("could not deserialize request body as yaml")]
+pub struct RejectYaml {
+    #[from]
+    source: serde_yaml::Error,
+}
+
+impl reject::Reject for RejectYaml {}
+
+impl RejectYaml {
+    pub fn wrap_into_reject(source: serde_yaml::Error) -> Rejection {
+        reject::custom(Self::from(source))
+    }
+
+    pub async fn recover_with<F>(&self, message: F) -> http::Response<hyper::Body>
+    where
+        F: FnOnce(&Self) -> String,
+    {
+        {
+            let error = self as &(dyn std::error::Error);
+            tracing::error!(error);
+        }
+        let message = message(self);
+        http::Response::builder()
+            .status(http::StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from(message))
+            .unwrap()
+    }
+
+    pub async fn recover(&self) -> http::Response<hyper::Body> {
+        self.recover_with(|_| String::new()).await
+    }
+}
+
+pub fn header() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    super::media_type("application/yaml")
+}
+
+pub fn body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send,
+{
+    warp::filters::body::bytes().and_then(deserialize_yaml::<T>)
+}
+
+async fn deserialize_yaml<T>(body: Bytes) -> Result<T, Rejection>
+where
+    T: DeserializeOwned + Send,
+{
+    from_bytes(&body)
+}
+
+pub fn from_bytes<T>(body: &[u8]) -> Result<T, Rejection>
+where
+    T: DeserializeOwned,
+{
+    let s = std::str::from_utf8(body).map_err(InvalidBodyEncoding::wrap_into_reject)?;
+    let t = serde_yaml::from_str(s).map_err(RejectYaml::wrap_into_reject)?;
+    Ok(t)
+}
+
+pub async fn recover(error: Rejection) -> Result<impl Reply, Rejection> {
+    if let Some(e) = error.find::<InvalidBodyEncoding>() {
+        let reply = e.recover().await;
+        return Ok(reply);
+    }
+    if let Some(e) = error.find::<RejectYaml>() {
+        let reply = e.recover().await;
+        return Ok(reply);
+    }
+    Err(error)
+}
+
+pub fn yaml_body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send,
+{
+    Filter::and(header(), body::<T>())
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::bucket::clients::ClientKey;
+use crate::bucket::milk::RefillRate;
+use crate::bucket::Liters;
+
+#[must_use]
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct BucketName(pub String);
+
+impl BucketName {
+    pub const COLUMN_NAME: &str = "name";
+
+    /// prefix of [`Self::client`]
+    pub const CLIENT_PREFIX: &str = "client:";
+
+    /// the limiter shared by every client of `/9/milk`
+    pub fn global() -> Self {
+        Self("global".to_string())
+    }
+
+    /// the bucket of a single client in [`ClientBuckets`](crate::bucket::ClientBuckets)
+    pub fn client(key: &ClientKey) -> Self {
+        Self(format!("{}{key}", Self::CLIENT_PREFIX))
+    }
+
+    /// the inverse of [`Self::client`]
+    pub fn client_key(&self) -> Option<ClientKey> {
+        self.0.strip_prefix(Self::CLIENT_PREFIX)?.parse().ok()
+    }
+}
+
+/// Level of a bucket in liters
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Deserialize, Serialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct SavedLevel(pub f64);
+
+impl SavedLevel {
+    pub const COLUMN_NAME: &str = "level";
+}
+
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, sqlx::FromRow)]
+pub struct MilkSnapshot {
+    pub name: BucketName,
+    pub level: SavedLevel,
+    pub saved_at: DateTime<Utc>,
+}
+
+impl MilkSnapshot {
+    pub const TABLE_NAME: &str = "milk_snapshots";
+    pub const SAVED_AT_COLUMN_NAME: &str = "saved_at";
+
+    /// Level at `now`, with the refill earned since the snapshot, capped at `full`
+    pub fn restore(&self, full: Liters, rate: RefillRate, now: DateTime<Utc>) -> Liters {
+        let downtime = (now - self.saved_at).to_std().unwrap_or_default();
+        let refilled = rate.refilled_in(downtime);
+        let level = self.level.0 as f32 + refilled.0;
+        Liters(level.clamp(0.0, full.0))
+    }
+}
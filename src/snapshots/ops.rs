@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use super::model::{BucketName, MilkSnapshot, SavedLevel};
+use super::Repository;
+use crate::bucket::limiter::RateLimiter;
+use crate::bucket::ClientBuckets;
+
+impl Repository {
+    /// Insert the snapshot, or overwrite the one of the same bucket
+    #[tracing::instrument(skip_all)]
+    pub async fn save(&self, snapshot: &MilkSnapshot) -> sqlx::Result<()> {
+        let query = format!(
+            r#"
+                INSERT INTO "{table}" ("{name}", "{level}", "{saved_at}") VALUES ($1, $2, $3)
+                ON CONFLICT ("{name}") DO UPDATE
+                SET "{level}" = EXCLUDED."{level}", "{saved_at}" = EXCLUDED."{saved_at}"
+            "#,
+            table = MilkSnapshot::TABLE_NAME,
+            name = BucketName::COLUMN_NAME,
+            level = SavedLevel::COLUMN_NAME,
+            saved_at = MilkSnapshot::SAVED_AT_COLUMN_NAME,
+        );
+        sqlx::query(&query)
+            .bind(&snapshot.name)
+            .bind(snapshot.level)
+            .bind(snapshot.saved_at)
+            .execute(&self.inner.pool)
+            .await?;
+        tracing::debug!(?snapshot, "Saved milk snapshot");
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn find(&self, name: &BucketName) -> sqlx::Result<Option<MilkSnapshot>> {
+        let query = format!(
+            r#"SELECT * FROM "{}" WHERE "{}" = $1"#,
+            MilkSnapshot::TABLE_NAME,
+            BucketName::COLUMN_NAME,
+        );
+        sqlx::query_as(&query)
+            .bind(name)
+            .fetch_optional(&self.inner.pool)
+            .await
+    }
+
+    /// Save the remaining liters of `limiter` as of now
+    #[tracing::instrument(skip_all)]
+    pub async fn save_limiter(
+        &self,
+        name: BucketName,
+        limiter: &dyn RateLimiter,
+    ) -> sqlx::Result<()> {
+        let remaining = limiter.quota().await.remaining();
+        let snapshot = MilkSnapshot {
+            name,
+            level: SavedLevel(remaining.0.max(0.0) as f64),
+            saved_at: Utc::now(),
+        };
+        self.save(&snapshot).await
+    }
+
+    /// Snapshots of every [`BucketName::client`]
+    #[tracing::instrument(skip_all)]
+    pub async fn find_clients(&self) -> sqlx::Result<Vec<MilkSnapshot>> {
+        let query = format!(
+            r#"SELECT * FROM "{}" WHERE starts_with("{}", $1)"#,
+            MilkSnapshot::TABLE_NAME,
+            BucketName::COLUMN_NAME,
+        );
+        sqlx::query_as(&query)
+            .bind(BucketName::CLIENT_PREFIX)
+            .fetch_all(&self.inner.pool)
+            .await
+    }
+
+    /// Replace the snapshots of every [`BucketName::client`] with the buckets kept now
+    ///
+    /// Evicted buckets were full, as are the ones missing on restore.
+    #[tracing::instrument(skip_all)]
+    pub async fn save_clients(&self, clients: &ClientBuckets) -> sqlx::Result<()> {
+        let delete = format!(
+            r#"DELETE FROM "{}" WHERE starts_with("{}", $1)"#,
+            MilkSnapshot::TABLE_NAME,
+            BucketName::COLUMN_NAME,
+        );
+        let insert = format!(
+            r#"INSERT INTO "{}" ("{}", "{}", "{}") VALUES ($1, $2, $3)"#,
+            MilkSnapshot::TABLE_NAME,
+            BucketName::COLUMN_NAME,
+            SavedLevel::COLUMN_NAME,
+            MilkSnapshot::SAVED_AT_COLUMN_NAME,
+        );
+        let statuses = clients.statuses().await;
+        let saved_at = Utc::now();
+        let mut tx = self.inner.pool.begin().await?;
+        sqlx::query(&delete)
+            .bind(BucketName::CLIENT_PREFIX)
+            .execute(&mut *tx)
+            .await?;
+        for status in &statuses {
+            sqlx::query(&insert)
+                .bind(BucketName::client(status.client()))
+                .bind(SavedLevel(status.level().0.max(0.0) as f64))
+                .bind(saved_at)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        tracing::debug!(clients = statuses.len(), "Saved milk snapshots of clients");
+        Ok(())
+    }
+
+    /// The global `limiter` and every bucket of `clients`
+    pub async fn save_milk(
+        &self,
+        limiter: &dyn RateLimiter,
+        clients: &ClientBuckets,
+    ) -> sqlx::Result<()> {
+        self.save_limiter(BucketName::global(), limiter).await?;
+        self.save_clients(clients).await
+    }
+
+    /// [`Self::save_milk`] every `period`, keeping on after failures
+    pub async fn save_task(
+        self,
+        limiter: Arc<dyn RateLimiter>,
+        clients: ClientBuckets,
+        period: Duration,
+    ) {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // the first tick completes immediately, right after the restore
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.save_milk(limiter.as_ref(), &clients).await {
+                let err = &e as &dyn std::error::Error;
+                tracing::error!(err, "failed to save milk snapshot");
+            }
+        }
+    }
+}
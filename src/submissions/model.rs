@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[must_use]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct SubmissionId(pub Uuid);
+
+impl SubmissionId {
+    pub const COLUMN_NAME: &str = "id";
+}
+
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct PackageName(pub String);
+
+impl PackageName {
+    pub const COLUMN_NAME: &str = "package_name";
+}
+
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct PackageVersion(pub String);
+
+impl PackageVersion {
+    pub const COLUMN_NAME: &str = "package_version";
+}
+
+#[must_use]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct CreatedAt(pub DateTime<Utc>);
+
+impl CreatedAt {
+    pub const COLUMN_NAME: &str = "created_at";
+}
+
+#[must_use]
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Item(pub String);
+
+impl Item {
+    pub const COLUMN_NAME: &str = "item";
+}
+
+#[must_use]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Quantity(pub i64);
+
+impl Quantity {
+    pub const COLUMN_NAME: &str = "quantity";
+}
+
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Order {
+    pub item: Item,
+    pub quantity: Quantity,
+}
+
+impl Order {
+    pub const TABLE_NAME: &str = "submission_orders";
+    pub const SUBMISSION_ID_COLUMN_NAME: &str = "submission_id";
+    pub const POSITION_COLUMN_NAME: &str = "position";
+}
+
+/// Row of `submissions` table
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::FromRow)]
+pub struct SubmissionRow {
+    pub id: SubmissionId,
+    pub package_name: PackageName,
+    pub package_version: Option<PackageVersion>,
+    pub created_at: CreatedAt,
+}
+
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Submission {
+    pub id: SubmissionId,
+    pub package_name: PackageName,
+    pub package_version: Option<PackageVersion>,
+    pub orders: Vec<Order>,
+    pub created_at: CreatedAt,
+}
+
+impl Submission {
+    pub const TABLE_NAME: &str = "submissions";
+
+    pub fn from_row(row: SubmissionRow, orders: Vec<Order>) -> Self {
+        let SubmissionRow {
+            id,
+            package_name,
+            package_version,
+            created_at,
+        } = row;
+        Self {
+            id,
+            package_name,
+            package_version,
+            orders,
+            created_at,
+        }
+    }
+}
+
+/// Total quantity of an item over submissions
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::FromRow)]
+pub struct ItemTotal {
+    pub item: Item,
+    pub total_quantity: Quantity,
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::model::{
+    CreatedAt, Item, ItemTotal, Order, PackageName, PackageVersion, Quantity, Submission,
+    SubmissionId, SubmissionRow,
+};
+use super::Repository;
+
+#[must_use]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateRequest {
+    pub package_name: PackageName,
+    pub package_version: Option<PackageVersion>,
+    pub orders: Vec<Order>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ListResponse {
+    pub submissions: Vec<Submission>,
+    pub page: u64,
+    pub next_page: Option<u64>,
+}
+
+/// Half-open range of `created_at`
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct DateRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl Repository {
+    #[tracing::instrument(skip_all)]
+    pub async fn create(&self, request: CreateRequest) -> sqlx::Result<Submission> {
+        let CreateRequest {
+            package_name,
+            package_version,
+            orders,
+        } = request;
+        let id = SubmissionId(Uuid::new_v4());
+        let mut tx = self.inner.pool.begin().await?;
+        let query = format!(
+            r#"INSERT INTO "{}" ("{}", "{}", "{}") VALUES ($1, $2, $3) RETURNING *"#,
+            Submission::TABLE_NAME,
+            SubmissionId::COLUMN_NAME,
+            PackageName::COLUMN_NAME,
+            PackageVersion::COLUMN_NAME,
+        );
+        let row: SubmissionRow = sqlx::query_as(&query)
+            .bind(id)
+            .bind(package_name)
+            .bind(package_version)
+            .fetch_one(&mut *tx)
+            .await?;
+        let query = format!(
+            r#"INSERT INTO "{}" ("{}", "{}", "{}", "{}") VALUES ($1, $2, $3, $4)"#,
+            Order::TABLE_NAME,
+            Order::SUBMISSION_ID_COLUMN_NAME,
+            Order::POSITION_COLUMN_NAME,
+            Item::COLUMN_NAME,
+            Quantity::COLUMN_NAME,
+        );
+        for (position, order) in orders.iter().enumerate() {
+            sqlx::query(&query)
+                .bind(id)
+                .bind(position as i32)
+                .bind(&order.item)
+                .bind(order.quantity)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        tracing::info!(orders = orders.len(), "INSERTed a submission");
+        Ok(Submission::from_row(row, orders))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn find_one(&self, id: SubmissionId) -> sqlx::Result<Option<Submission>> {
+        let query = format!(
+            r#"SELECT * FROM "{}" WHERE "{}" = $1 LIMIT 1"#,
+            Submission::TABLE_NAME,
+            SubmissionId::COLUMN_NAME
+        );
+        let row: Option<SubmissionRow> = sqlx::query_as(&query)
+            .bind(id)
+            .fetch_optional(&self.inner.pool)
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut orders = self.orders_of(&[row.id]).await?;
+        let orders = orders.remove(&row.id).unwrap_or_default();
+        tracing::info!("SELECTed a submission");
+        Ok(Some(Submission::from_row(row, orders)))
+    }
+
+    /// `page` starts from 1
+    #[tracing::instrument(skip_all)]
+    pub async fn list(&self, page: u64, per_page: u64) -> sqlx::Result<ListResponse> {
+        let page = page.max(1);
+        let query = format!(
+            r#"SELECT * FROM "{}" ORDER BY "{}" DESC, "{}" DESC LIMIT $1 OFFSET $2"#,
+            Submission::TABLE_NAME,
+            CreatedAt::COLUMN_NAME,
+            SubmissionId::COLUMN_NAME
+        );
+        // fetch one more row to know whether the next page exists
+        let limit = i64::try_from(per_page.saturating_add(1)).unwrap_or(i64::MAX);
+        let offset = i64::try_from((page - 1).saturating_mul(per_page))
+            .map_err(|e| sqlx::Error::Encode(e.into()))?;
+        let mut rows: Vec<SubmissionRow> = sqlx::query_as(&query)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.inner.pool)
+            .await?;
+        let next_page = (rows.len() as u64 > per_page)
+            .then(|| page.checked_add(1))
+            .flatten();
+        rows.truncate(per_page as usize);
+        let ids: Vec<_> = rows.iter().map(|r| r.id).collect();
+        let mut orders = self.orders_of(&ids).await?;
+        let submissions = rows
+            .into_iter()
+            .map(|r| {
+                let o = orders.remove(&r.id).unwrap_or_default();
+                Submission::from_row(r, o)
+            })
+            .collect();
+        tracing::info!("Listed submissions");
+        Ok(ListResponse {
+            submissions,
+            page,
+            next_page,
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn totals(&self, range: DateRange) -> sqlx::Result<Vec<ItemTotal>> {
+        let query = format!(
+            r#"
+                SELECT o."{item}" AS "item", SUM(o."{quantity}")::BIGINT AS "total_quantity"
+                FROM "{orders}" o
+                JOIN "{submissions}" s ON s."{id}" = o."{submission_id}"
+                WHERE ($1::TIMESTAMPTZ IS NULL OR s."{created_at}" >= $1)
+                  AND ($2::TIMESTAMPTZ IS NULL OR s."{created_at}" < $2)
+                GROUP BY o."{item}"
+                ORDER BY o."{item}" ASC
+            "#,
+            item = Item::COLUMN_NAME,
+            quantity = Quantity::COLUMN_NAME,
+            orders = Order::TABLE_NAME,
+            submissions = Submission::TABLE_NAME,
+            id = SubmissionId::COLUMN_NAME,
+            submission_id = Order::SUBMISSION_ID_COLUMN_NAME,
+            created_at = CreatedAt::COLUMN_NAME,
+        );
+        let totals = sqlx::query_as(&query)
+            .bind(range.from)
+            .bind(range.to)
+            .fetch_all(&self.inner.pool)
+            .await?;
+        Ok(totals)
+    }
+
+    async fn orders_of(
+        &self,
+        ids: &[SubmissionId],
+    ) -> sqlx::Result<HashMap<SubmissionId, Vec<Order>>> {
+        #[derive(sqlx::FromRow)]
+        struct Row {
+            submission_id: SubmissionId,
+            #[sqlx(flatten)]
+            order: Order,
+        }
+
+        let query = format!(
+            r#"
+                SELECT * FROM "{table}"
+                WHERE "{submission_id}" = ANY($1)
+                ORDER BY "{submission_id}", "{position}" ASC
+            "#,
+            table = Order::TABLE_NAME,
+            submission_id = Order::SUBMISSION_ID_COLUMN_NAME,
+            position = Order::POSITION_COLUMN_NAME,
+        );
+        let ids: Vec<Uuid> = ids.iter().map(|i| i.0).collect();
+        let rows: Vec<Row> = sqlx::query_as(&query)
+            .bind(ids)
+            .fetch_all(&self.inner.pool)
+            .await?;
+        let mut orders: HashMap<_, Vec<_>> = HashMap::new();
+        for Row {
+            submission_id,
+            order,
+        } in rows
+        {
+            orders.entry(submission_id).or_default().push(order);
+        }
+        Ok(orders)
+    }
+}
@@ -0,0 +1,142 @@
+//! Pricing and reservation of manifest orders against the catalog, which need a database
+
+mod common;
+
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use warp::http::StatusCode;
+
+use lib::catalog::model::{CatalogItem, ItemName, Stock, UnitPrice};
+use lib::catalog::ops::ReserveError;
+use shuttlings_cch24 as lib;
+
+fn item(name: &str, stock: i64, unit_price: i64) -> CatalogItem {
+    CatalogItem {
+        name: ItemName(name.to_string()),
+        stock: Stock(stock),
+        unit_price: UnitPrice(unit_price),
+    }
+}
+
+async fn catalog(pool: &PgPool) -> lib::catalog::Repository {
+    let repository = lib::catalog::Repository::builder()
+        .pool(pool.clone())
+        .build();
+    repository
+        .upsert_many(&[item("Toy car", 10, 1250), item("Doll", 1, 800)])
+        .await
+        .unwrap();
+    repository
+}
+
+async fn stock(repository: &lib::catalog::Repository, name: &str) -> i64 {
+    let name = ItemName(name.to_string());
+    let items = repository.find_many(&[name.clone()]).await.unwrap();
+    items[&name].stock.0
+}
+
+fn request(name: &str, quantity: i64) -> (ItemName, Stock) {
+    (ItemName(name.to_string()), Stock(quantity))
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn reserve_takes_every_item_or_none(pool: PgPool) {
+    let repository = catalog(&pool).await;
+
+    repository
+        .reserve(&[request("Toy car", 4), request("Doll", 1)])
+        .await
+        .unwrap();
+    assert_eq!(stock(&repository, "Toy car").await, 6);
+    assert_eq!(stock(&repository, "Doll").await, 0);
+
+    let res = repository
+        .reserve(&[request("Toy car", 1), request("Doll", 1)])
+        .await;
+    assert!(
+        matches!(res, Err(ReserveError::InsufficientStock(ref n)) if n.0 == "Doll"),
+        "{res:?}"
+    );
+    assert_eq!(stock(&repository, "Toy car").await, 6);
+
+    let res = repository
+        .reserve(&[request("Toy car", 1), request("Kite", 1)])
+        .await;
+    assert!(
+        matches!(res, Err(ReserveError::UnknownItem(ref n)) if n.0 == "Kite"),
+        "{res:?}"
+    );
+    assert_eq!(stock(&repository, "Toy car").await, 6);
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn release_gives_back_what_reserve_took(pool: PgPool) {
+    let repository = catalog(&pool).await;
+    let requests = [request("Toy car", 4), request("Doll", 1)];
+
+    repository.reserve(&requests).await.unwrap();
+    repository.release(&requests).await.unwrap();
+    assert_eq!(stock(&repository, "Toy car").await, 10);
+    assert_eq!(stock(&repository, "Doll").await, 1);
+}
+
+async fn order(pool: PgPool, metadata: &str) -> (StatusCode, Value) {
+    let manifest = format!(
+        "[package]\nname = \"catalog\"\nkeywords = [\"Christmas 2024\"]\n\n[package.metadata]\n{metadata}"
+    );
+    let res = warp::test::request()
+        .method("POST")
+        .path("/5/manifest?format=json")
+        .header("content-type", "application/toml")
+        .body(manifest)
+        .reply(&common::routes_with(pool))
+        .await;
+    let body = serde_json::from_slice(res.body()).unwrap();
+    (res.status(), body)
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn invoice_lines_are_priced_and_unknown_items_flagged(pool: PgPool) {
+    let _ = catalog(&pool).await;
+    let metadata = r#"orders = [
+    { item = "Toy car", quantity = 2 },
+    { item = "Kite", quantity = 1 },
+    { item = "Doll", quantity = 3 },
+]
+"#;
+    let (status, body) = order(pool, metadata).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body["invoice"],
+        json!({
+            "lines": [
+                { "item": "Toy car", "quantity": 2, "unit_price": 1250, "line_total": 2500, "status": "available" },
+                { "item": "Kite", "quantity": 1, "unit_price": null, "line_total": null, "status": "unknown_item" },
+                { "item": "Doll", "quantity": 3, "unit_price": 800, "line_total": 2400, "status": "insufficient_stock" },
+            ],
+            "total": 4900,
+            "reserved": false,
+        })
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn confirmed_orders_reserve_stock(pool: PgPool) {
+    let repository = catalog(&pool).await;
+    let metadata = "confirm = true\norders = [{ item = \"Toy car\", quantity = 4 }]\n";
+
+    let (status, body) = order(pool.clone(), metadata).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["invoice"]["reserved"], true);
+    assert_eq!(stock(&repository, "Toy car").await, 6);
+
+    let metadata = "confirm = true\norders = [{ item = \"Toy car\", quantity = 7 }]\n";
+    let (status, body) = order(pool, metadata).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    assert_eq!(body["invoice"]["reserved"], false);
+    assert_eq!(stock(&repository, "Toy car").await, 6);
+}
@@ -0,0 +1,81 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use warp::Filter;
+
+use lib::bucket::{milk::RefillRate, ClientBuckets, Liters, MilkBucket};
+use shuttlings_cch24 as lib;
+
+pub const MANIFEST_KEYWORD: &str = "Christmas 2024";
+
+/// State whose database is never connected to, so only endpoints which do not touch it work
+pub fn state() -> lib::routes::State {
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .connect_lazy("postgres://postgres@localhost/unused")
+        .unwrap();
+    state_with(pool)
+}
+
+/// State whose repositories use `pool`
+pub fn state_with(pool: sqlx::PgPool) -> lib::routes::State {
+    let rate = RefillRate::new(Liters(1.0), Duration::from_secs(3600));
+    lib::routes::State::builder()
+        .seek_url("https://example.com")
+        .manifest_keyword(MANIFEST_KEYWORD)
+        .milk_limiter(std::sync::Arc::new(
+            MilkBucket::builder()
+                .full(5.0)
+                .initial(5.0)
+                .refill_rate(rate)
+                .build(),
+        ))
+        .milk_clients(ClientBuckets::builder().full(5.0).refill_rate(rate).build())
+        .jwt_manager(
+            lib::jwt::Manager::builder()
+                .issuer("test")
+                .key("test")
+                .expires_in(chrono::TimeDelta::seconds(60))
+                .build(),
+        )
+        .cookie_manager(lib::cookie::Manager::builder().name("gift").build())
+        .jwt_decoder(lib::jwt::Decoder::builder().pem(vec![]).build())
+        .quotes_repository(
+            lib::quotes::Repository::builder()
+                .pool(pool.clone())
+                .build(),
+        )
+        .submissions_repository(
+            lib::submissions::Repository::builder()
+                .pool(pool.clone())
+                .build(),
+        )
+        .catalog_repository(lib::catalog::Repository::builder().pool(pool).build())
+        .admin_token(None)
+        .build()
+}
+
+pub fn routes() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    lib::routes::make(state())
+}
+
+pub fn routes_with(
+    pool: sqlx::PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    lib::routes::make(state_with(pool))
+}
+
+const BOUNDARY: &str = "test-boundary";
+
+/// `multipart/form-data` content type and body of `parts`
+pub fn multipart(parts: &[(&str, &str)]) -> (String, String) {
+    let mut body = String::new();
+    for (name, content) in parts {
+        body += &format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{content}\r\n"
+        );
+    }
+    body += &format!("--{BOUNDARY}--\r\n");
+    let content_type = format!("multipart/form-data; boundary={BOUNDARY}");
+    (content_type, body)
+}
@@ -0,0 +1,363 @@
+mod common;
+
+use warp::http::StatusCode;
+
+use lib::connect4::{Game, GameStatus, Size, Team};
+use shuttlings_cch24 as lib;
+
+use Team::{Cookie, Milk};
+
+/// `moves` of (team, column from 0) piled in order on a board of `size`
+fn play(size: Size, moves: &[(Team, usize)]) -> Game {
+    let mut game = Game::with_size(size);
+    for (i, &(team, col)) in moves.iter().enumerate() {
+        assert_eq!(game.status(), GameStatus::Playing, "before move {i}");
+        game.pile(team, col).unwrap();
+    }
+    game
+}
+
+fn size(width: usize, height: usize, win_length: usize) -> Size {
+    Size::new(width, height, win_length).unwrap()
+}
+
+#[test]
+fn rows_win_on_non_square_boards() {
+    // along the bottom edge, up to the right edge
+    let moves = [(Cookie, 3), (Cookie, 4), (Cookie, 5)];
+    let game = play(Size::CLASSIC, &moves);
+    assert_eq!(game.status(), GameStatus::Playing);
+    let game = play(Size::CLASSIC, &[&moves[..], &[(Cookie, 6)]].concat());
+    assert_eq!(game.status(), GameStatus::Wins(Cookie));
+
+    // along the top edge, above columns of alternating tiles
+    let mut moves = vec![];
+    for col in 0..4 {
+        let teams = if col < 2 {
+            [Milk, Cookie]
+        } else {
+            [Cookie, Milk]
+        };
+        moves.extend(teams.iter().cycle().take(5).map(|&team| (team, col)));
+    }
+    let mut game = play(Size::CLASSIC, &moves);
+    for col in 0..4 {
+        assert_eq!(game.status(), GameStatus::Playing, "{col}");
+        game.pile(Milk, col).unwrap();
+    }
+    assert_eq!(game.status(), GameStatus::Wins(Milk));
+}
+
+#[test]
+fn columns_win_on_non_square_boards() {
+    // against the left and right edges, reaching the top
+    for col in [0, 6] {
+        let mut moves = vec![(Cookie, col), (Cookie, col)];
+        moves.extend([(Milk, col); 3]);
+        let game = play(Size::CLASSIC, &moves);
+        assert_eq!(game.status(), GameStatus::Playing, "{col}");
+        let mut game = game;
+        game.pile(Milk, col).unwrap();
+        assert_eq!(game.status(), GameStatus::Wins(Milk), "{col}");
+    }
+    // taller than wide
+    let game = play(size(2, 5, 5), &[(Cookie, 1); 5]);
+    assert_eq!(game.status(), GameStatus::Wins(Cookie));
+}
+
+#[test]
+fn diagonals_win_both_ways() {
+    for offset in [0, 3] {
+        // rising to the right
+        let rising = [
+            (Cookie, 0),
+            (Milk, 1),
+            (Cookie, 1),
+            (Milk, 2),
+            (Milk, 2),
+            (Cookie, 2),
+            (Milk, 3),
+            (Milk, 3),
+            (Milk, 3),
+            (Cookie, 3),
+        ];
+        // falling to the right
+        let falling = rising.map(|(team, col)| (team, 3 - col));
+        for moves in [rising, falling] {
+            let moves = moves.map(|(team, col)| (team, col + offset));
+            let game = play(Size::CLASSIC, &moves[..moves.len() - 1]);
+            assert_eq!(game.status(), GameStatus::Playing, "{moves:?}");
+            let game = play(Size::CLASSIC, &moves);
+            assert_eq!(game.status(), GameStatus::Wins(Cookie), "{moves:?}");
+        }
+    }
+}
+
+#[test]
+fn win_lengths_below_the_side_win_early() {
+    let game = play(size(5, 5, 3), &[(Milk, 2), (Milk, 3), (Milk, 4)]);
+    assert_eq!(game.status(), GameStatus::Wins(Milk));
+    let game = play(size(5, 5, 3), &[(Cookie, 0), (Milk, 1), (Cookie, 1)]);
+    assert_eq!(game.status(), GameStatus::Playing);
+    let game = play(
+        size(5, 5, 3),
+        &[(Cookie, 0), (Milk, 1), (Cookie, 1), (Milk, 2)],
+    );
+    assert_eq!(game.status(), GameStatus::Playing);
+    let game = play(size(3, 3, 1), &[(Milk, 1)]);
+    assert_eq!(game.status(), GameStatus::Wins(Milk));
+}
+
+#[test]
+fn runs_do_not_wrap_around_the_board() {
+    // the end of the bottom row and the start of the next one
+    let moves = [(Cookie, 3), (Cookie, 4), (Milk, 0), (Cookie, 0)];
+    let game = play(size(5, 3, 3), &moves);
+    assert_eq!(game.status(), GameStatus::Playing);
+    // the bottom of a column and the top of the next one
+    let moves = [
+        (Milk, 0),
+        (Milk, 0),
+        (Cookie, 0),
+        (Cookie, 1),
+        (Milk, 1),
+        (Milk, 1),
+    ];
+    let game = play(size(2, 3, 3), &moves);
+    assert_eq!(game.status(), GameStatus::NoWinner);
+}
+
+#[test]
+fn full_boards_without_a_run_have_no_winner() {
+    // pairs of columns alternating, so no three in any line
+    let mut moves = vec![];
+    for col in 0..4 {
+        for row in 0..4 {
+            let team = if (col / 2 + row) % 2 == 0 {
+                Cookie
+            } else {
+                Milk
+            };
+            moves.push((team, col));
+        }
+    }
+    let game = play(size(4, 4, 3), &moves[..moves.len() - 1]);
+    assert_eq!(game.status(), GameStatus::Playing);
+    let game = play(size(4, 4, 3), &moves);
+    assert_eq!(game.status(), GameStatus::NoWinner);
+}
+
+async fn request(
+    method: &str,
+    path: &str,
+    routes: &(impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection>
+          + Clone
+          + 'static),
+) -> (StatusCode, String) {
+    let res = warp::test::request()
+        .method(method)
+        .path(path)
+        .reply(routes)
+        .await;
+    let body = String::from_utf8(res.body().to_vec()).unwrap();
+    (res.status(), body)
+}
+
+#[tokio::test]
+async fn legacy_board_is_unchanged() {
+    let routes = common::routes();
+    let empty = "\
+⬜⬛⬛⬛⬛⬜
+⬜⬛⬛⬛⬛⬜
+⬜⬛⬛⬛⬛⬜
+⬜⬛⬛⬛⬛⬜
+⬜⬜⬜⬜⬜⬜
+";
+    assert_eq!(request("GET", "/12/board", &routes).await.1, empty);
+    assert_eq!(request("POST", "/12/reset", &routes).await.1, empty);
+
+    for col in 1..4 {
+        request("POST", &format!("/12/place/cookie/{col}"), &routes).await;
+    }
+    let (status, body) = request("POST", "/12/place/cookie/4", &routes).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        "\
+⬜⬛⬛⬛⬛⬜
+⬜⬛⬛⬛⬛⬜
+⬜⬛⬛⬛⬛⬜
+⬜🍪🍪🍪🍪⬜
+⬜⬜⬜⬜⬜⬜
+🍪 wins!
+"
+    );
+    let (status, _) = request("POST", "/12/place/milk/1", &routes).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn legacy_random_boards_are_unchanged() {
+    let routes = common::routes();
+    let (_, first) = request("GET", "/12/random-board", &routes).await;
+    assert_eq!(
+        first,
+        "\
+⬜🍪🍪🍪🍪⬜
+⬜🥛🍪🍪🥛⬜
+⬜🥛🥛🥛🥛⬜
+⬜🍪🥛🍪🥛⬜
+⬜⬜⬜⬜⬜⬜
+🍪 wins!
+"
+    );
+    let (_, second) = request("GET", "/12/random-board", &routes).await;
+    assert_eq!(
+        second,
+        "\
+⬜🍪🥛🍪🍪⬜
+⬜🥛🍪🥛🍪⬜
+⬜🥛🍪🍪🍪⬜
+⬜🍪🥛🥛🥛⬜
+⬜⬜⬜⬜⬜⬜
+No winner.
+"
+    );
+    // the sequence starts over on reset
+    request("POST", "/12/reset", &routes).await;
+    assert_eq!(request("GET", "/12/random-board", &routes).await.1, first);
+}
+
+#[test]
+fn strict_games_check_the_column_before_the_turn() {
+    let mut game = Game::new().with_turns(Milk);
+    assert!(matches!(
+        game.pile(Cookie, 4),
+        Err(lib::connect4::GameError::InvalidColumn(4))
+    ));
+    assert!(matches!(
+        game.pile(Cookie, 0),
+        Err(lib::connect4::GameError::OutOfTurn { .. })
+    ));
+    game.pile(Milk, 0).unwrap();
+    assert_eq!(game.next(), Some(Cookie));
+}
+
+#[test]
+fn random_boards_hand_the_turn_on() {
+    for (size, next) in [(size(4, 4, 4), Cookie), (size(3, 3, 3), Milk)] {
+        let mut game = Game::with_size(size).with_turns(Cookie);
+        game.random_board();
+        assert_eq!(game.next(), Some(next), "{size:?}");
+    }
+    let mut game = Game::new();
+    game.random_board();
+    assert_eq!(game.next(), None);
+}
+
+async fn create(
+    body: &'static str,
+    routes: &(impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection>
+          + Clone
+          + 'static),
+) -> (StatusCode, serde_json::Value) {
+    let res = warp::test::request()
+        .method("POST")
+        .path("/12/games")
+        .body(body)
+        .reply(routes)
+        .await;
+    let json = serde_json::from_slice(res.body()).unwrap_or_default();
+    (res.status(), json)
+}
+
+async fn reset(
+    path: &str,
+    body: &'static str,
+    routes: &(impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection>
+          + Clone
+          + 'static),
+) -> (StatusCode, String) {
+    let res = warp::test::request()
+        .method("POST")
+        .path(path)
+        .body(body)
+        .reply(routes)
+        .await;
+    let body = String::from_utf8(res.body().to_vec()).unwrap();
+    (res.status(), body)
+}
+
+#[tokio::test]
+async fn strict_games_take_turns() {
+    let routes = common::routes();
+    let (status, created) = create(r#"{"strict": true}"#, &routes).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["next"], "cookie");
+    let game = format!("/12/games/{}", created["id"].as_str().unwrap());
+
+    let (status, body) = request("GET", &format!("{game}/board"), &routes).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("Next: 🍪\n"), "{body}");
+    let (status, body) = request("POST", &format!("{game}/place/milk/1"), &routes).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(body.ends_with("Next: 🍪\n"), "{body}");
+    // a bad column is bad whoever plays it
+    let (status, _) = request("POST", &format!("{game}/place/milk/5"), &routes).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = request("POST", &format!("{game}/place/cookie/1"), &routes).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("Next: 🥛\n"), "{body}");
+    let (status, _) = request("POST", &format!("{game}/place/cookie/1"), &routes).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = request("POST", &format!("{game}/place/milk/2"), &routes).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn strict_games_start_with_the_first_team() {
+    let routes = common::routes();
+    let (status, created) = create(r#"{"strict": true, "first": "milk"}"#, &routes).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["next"], "milk");
+    let game = format!("/12/games/{}", created["id"].as_str().unwrap());
+    request("POST", &format!("{game}/place/milk/1"), &routes).await;
+
+    // reset keeps the first team unless another one is given
+    let (status, body) = reset(&format!("{game}/reset"), "", &routes).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("Next: 🥛\n"), "{body}");
+    let (status, body) = reset(&format!("{game}/reset"), r#"{"first": "cookie"}"#, &routes).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("Next: 🍪\n"), "{body}");
+    let (status, body) = reset(&format!("{game}/reset"), "", &routes).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("Next: 🍪\n"), "{body}");
+
+    let (status, _) = reset(&format!("{game}/reset"), r#"{"first": "tea"}"#, &routes).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn first_teams_need_strict_mode() {
+    let routes = common::routes();
+    for body in [
+        r#"{"first": "milk"}"#,
+        r#"{"strict": false, "first": "cookie"}"#,
+    ] {
+        let (status, _) = create(body, &routes).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    }
+    let (status, created) = create("{}", &routes).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created.get("next"), None);
+
+    let game = format!("/12/games/{}", created["id"].as_str().unwrap());
+    for path in ["/12/reset", &format!("{game}/reset")] {
+        let (status, _) = reset(path, r#"{"first": "milk"}"#, &routes).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{path}");
+        let (status, body) = reset(path, "", &routes).await;
+        assert_eq!(status, StatusCode::OK, "{path}");
+        assert!(!body.contains("Next"), "{body}");
+    }
+}
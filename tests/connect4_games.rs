@@ -0,0 +1,151 @@
+mod common;
+
+use std::time::Duration;
+
+use warp::http::StatusCode;
+
+use lib::connect4::{Game, GameId, Games};
+use shuttlings_cch24 as lib;
+
+async fn request(
+    routes: &(impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection>
+          + Clone
+          + 'static),
+    method: &str,
+    path: &str,
+) -> warp::http::Response<bytes::Bytes> {
+    warp::test::request()
+        .method(method)
+        .path(path)
+        .reply(routes)
+        .await
+}
+
+fn text(res: &warp::http::Response<bytes::Bytes>) -> &str {
+    std::str::from_utf8(res.body()).unwrap()
+}
+
+#[tokio::test]
+async fn games_are_addressed_by_id() {
+    let routes = common::routes();
+    let res = request(&routes, "POST", "/12/games").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let location = res.headers()["location"].to_str().unwrap().to_string();
+    let empty = text(&request(&routes, "GET", "/12/board").await).to_string();
+
+    let res = request(&routes, "POST", &format!("{location}/place/cookie/1")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let placed = text(&res).to_string();
+    assert_ne!(placed, empty);
+    assert_eq!(
+        text(&request(&routes, "GET", &format!("{location}/board")).await),
+        placed
+    );
+    // other games are untouched
+    assert_eq!(text(&request(&routes, "GET", "/12/board").await), empty);
+
+    let unknown = format!("/12/games/{}/board", uuid::Uuid::new_v4());
+    let res = request(&routes, "GET", &unknown).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = request(&routes, "GET", "/12/games/nope/board").await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn legacy_routes_play_the_default_game() {
+    let routes = common::routes();
+    let res = request(&routes, "POST", "/12/place/milk/2").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let placed = text(&res).to_string();
+    for path in ["/12/board", "/12/games/default/board"] {
+        assert_eq!(text(&request(&routes, "GET", path).await), placed, "{path}");
+    }
+    let nil = format!("/12/games/{}/board", uuid::Uuid::nil());
+    assert_eq!(text(&request(&routes, "GET", &nil).await), placed);
+
+    let res = request(&routes, "POST", "/12/games/default/reset").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        text(&request(&routes, "GET", "/12/board").await),
+        text(&res)
+    );
+    assert_ne!(text(&res), placed);
+}
+
+#[tokio::test]
+async fn deleted_games_are_gone() {
+    let routes = common::routes();
+    let res = request(&routes, "POST", "/12/games").await;
+    let location = res.headers()["location"].to_str().unwrap().to_string();
+
+    let res = request(&routes, "DELETE", &location).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = request(&routes, "GET", &format!("{location}/board")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = request(&routes, "DELETE", &location).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = request(&routes, "DELETE", "/12/games/default").await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let res = request(&routes, "GET", "/12/board").await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn games_are_listed_with_the_default_first() {
+    let games = Games::default();
+    let a = games.create(Game::new()).await.unwrap();
+    let b = games.create(Game::new()).await.unwrap();
+    // listing while a game is held keeps the others usable
+    let held = games.get(a).await.unwrap();
+    let _held = held.lock().await;
+    let list = tokio::spawn({
+        let games = games.clone();
+        async move { games.list().await }
+    });
+    tokio::task::yield_now().await;
+    let got = tokio::time::timeout(Duration::from_secs(1), games.get(b)).await;
+    assert!(got.expect("the list holds all games").is_some());
+    drop(_held);
+
+    let list = serde_json::to_value(list.await.unwrap()).unwrap();
+    let ids: Vec<_> = list
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|g| g["id"].as_str().unwrap().to_string())
+        .collect();
+    let mut expected = vec![a.to_string(), b.to_string()];
+    expected.sort();
+    expected.insert(0, GameId::DEFAULT.to_string());
+    assert_eq!(ids, expected);
+}
+
+#[tokio::test]
+async fn games_beyond_the_limit_are_not_created() {
+    let games = Games::builder().max_games(2).build();
+    let a = games.create(Game::new()).await.unwrap();
+    games.create(Game::new()).await.unwrap();
+    assert!(games.create(Game::new()).await.is_err());
+
+    games.delete(a).await.unwrap();
+    assert!(games.create(Game::new()).await.is_ok());
+}
+
+#[tokio::test(start_paused = true)]
+async fn idle_games_are_evicted() {
+    let games = Games::builder()
+        .idle_timeout(Duration::from_secs(60))
+        .build();
+    let idle = games.create(Game::new()).await.unwrap();
+    let active = games.create(Game::new()).await.unwrap();
+
+    tokio::time::advance(Duration::from_secs(45)).await;
+    assert!(games.get(active).await.is_some());
+    tokio::time::advance(Duration::from_secs(30)).await;
+    assert_eq!(games.evict_idle().await, 1);
+    assert!(games.get(idle).await.is_none());
+    assert!(games.get(active).await.is_some());
+    // never the default game
+    assert!(games.get(GameId::DEFAULT).await.is_some());
+}
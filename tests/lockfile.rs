@@ -0,0 +1,135 @@
+mod common;
+
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["serde 1.0.0", "serde 1.0.200", "local"]
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "337d7f3c8a1b2e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "local"
+version = "0.2.0"
+source = "git+https://example.com/local.git#abcdef"
+"#;
+
+async fn report(query: &str, lockfile: &str) -> (StatusCode, String, String) {
+    let res = warp::test::request()
+        .method("POST")
+        .path(&format!("/5/lockfile{query}"))
+        .header("content-type", "application/toml")
+        .body(lockfile)
+        .reply(&common::routes())
+        .await;
+    let content_type = res
+        .headers()
+        .get("content-type")
+        .map(|v| v.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    let body = String::from_utf8(res.body().to_vec()).unwrap();
+    (res.status(), content_type, body)
+}
+
+#[tokio::test]
+async fn report_lists_duplicates_foreign_sources_and_checksums() {
+    let (status, content_type, body) = report("", LOCKFILE).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(content_type, "application/json");
+    let report: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        report,
+        json!({
+            "packages": 4,
+            "duplicates": [{ "name": "serde", "versions": ["1.0.0", "1.0.200"] }],
+            "foreign_sources": [{
+                "name": "local",
+                "version": "0.2.0",
+                "source": "git+https://example.com/local.git#abcdef",
+                "kind": "git",
+            }],
+            "checksums": {
+                "present": 1,
+                "missing": [{ "name": "serde", "version": "1.0.200" }],
+            },
+        })
+    );
+}
+
+#[tokio::test]
+async fn v1_checksums_are_read_from_metadata() {
+    let lockfile = r#"
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[metadata]
+"checksum serde 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)" = "337d7f3c8a1b2e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f"
+"#;
+    let (status, _, body) = report("", lockfile).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let report: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(report["checksums"], json!({ "present": 1, "missing": [] }));
+}
+
+#[tokio::test]
+async fn bad_checksums_are_rejected() {
+    for checksum in [
+        "337d7f",
+        "zz7d7f3c8a1b2e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f",
+    ] {
+        let lockfile = format!(
+            "[[package]]\nname = \"serde\"\nversion = \"1.0.0\"\nchecksum = \"{checksum}\"\n"
+        );
+        for query in ["", "?format=html"] {
+            let (status, _, body) = report(query, &lockfile).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{checksum}{query}");
+            assert_eq!(body, "Invalid lockfile");
+        }
+    }
+}
+
+#[tokio::test]
+async fn html_has_a_swatch_per_checksum() {
+    let (status, content_type, body) = report("?format=html", LOCKFILE).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(content_type, "text/html; charset=utf-8");
+    assert!(body.starts_with("<!DOCTYPE html>\n"), "{body}");
+    let swatches: Vec<_> = body
+        .lines()
+        .filter(|l| l.starts_with(r#"<div class="swatch""#))
+        .collect();
+    assert_eq!(
+        swatches,
+        [r##"<div class="swatch" style="background-color:#337d7f;" title="serde 1.0.0"></div>"##]
+    );
+}
+
+#[tokio::test]
+async fn html_titles_are_escaped() {
+    let lockfile = r#"
+[[package]]
+name = "<script>"
+version = "1.0.0"
+checksum = "0000003c8a1b2e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f"
+"#;
+    let (status, _, body) = report("?format=html", lockfile).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body.contains(r#"title="&lt;script&gt; 1.0.0""#), "{body}");
+    assert!(!body.contains("<script>"), "{body}");
+}
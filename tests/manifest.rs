@@ -0,0 +1,301 @@
+mod common;
+
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+const WORKSPACE: &str = r#"
+[workspace]
+members = ["member"]
+
+[workspace.package]
+version = "1.2.3"
+keywords = ["Christmas 2024"]
+
+[workspace.package.metadata]
+orders = [{ item = "Toy car", quantity = 2 }, { item = "Lego brick", quantity = 230 }]
+"#;
+
+async fn verbose_report(parts: &[(&str, &str)]) -> (StatusCode, Value) {
+    let (content_type, body) = common::multipart(parts);
+    let res = warp::test::request()
+        .method("POST")
+        .path("/5/manifest?verbose=true")
+        .header("content-type", content_type)
+        .body(body)
+        .reply(&common::routes())
+        .await;
+    let report = serde_json::from_slice(res.body()).unwrap();
+    (res.status(), report)
+}
+
+#[tokio::test]
+async fn inherits_keywords_and_metadata_from_workspace_package() {
+    let member = r#"
+[package]
+name = "member"
+version.workspace = true
+keywords.workspace = true
+"#;
+    let (status, report) = verbose_report(&[("manifest", member), ("workspace", WORKSPACE)]).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["problems"], json!([]));
+    assert_eq!(
+        report["orders"],
+        json!([
+            { "item": "Toy car", "quantity": 2 },
+            { "item": "Lego brick", "quantity": 230 },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn member_metadata_takes_precedence() {
+    let member = r#"
+[package]
+name = "member"
+keywords.workspace = true
+
+[package.metadata]
+orders = [{ item = "Cookie", quantity = 1 }]
+"#;
+    let (status, report) = verbose_report(&[("manifest", member), ("workspace", WORKSPACE)]).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(
+        report["orders"],
+        json!([{ "item": "Cookie", "quantity": 1 }])
+    );
+}
+
+#[tokio::test]
+async fn local_keywords_are_not_replaced() {
+    let member = r#"
+[package]
+name = "member"
+keywords = ["Easter"]
+"#;
+    let (status, report) = verbose_report(&[("manifest", member), ("workspace", WORKSPACE)]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let problems = report["problems"].as_array().unwrap();
+    assert_eq!(problems.len(), 1, "{report}");
+    assert_eq!(
+        problems[0]["message"],
+        r#"Magic keyword not provided: none of ["Christmas 2024"] found in keywords"#
+    );
+}
+
+#[tokio::test]
+async fn problems_name_the_part() {
+    let member = "[package]\nname = \"member\"\n";
+    let (status, report) = verbose_report(&[("manifest", member)]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(report["problems"][0]["part"], "workspace");
+
+    let (status, report) =
+        verbose_report(&[("manifest", member), ("workspace", "[workspace\n")]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(report["problems"][0]["part"], "workspace");
+    assert_eq!(report["problems"][0]["span"]["start"]["line"], 1);
+}
+
+#[tokio::test]
+async fn verbose_raw_manifest_is_still_reported() {
+    let res = warp::test::request()
+        .method("POST")
+        .path("/5/manifest?verbose=true")
+        .header("content-type", "application/toml")
+        .body("[package]\nname = \"member\"\nkeywords = [\"Christmas 2024\"]\n")
+        .reply(&common::routes())
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let report: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(report["problems"], json!([]));
+}
+
+#[tokio::test]
+async fn unresolved_workspace_keywords_are_rejected() {
+    let member = r#"
+[package]
+name = "member"
+keywords.workspace = true
+"#;
+    let root = "[workspace]\nmembers = [\"member\"]\n";
+    let (content_type, body) = common::multipart(&[("manifest", member), ("workspace", root)]);
+    let res = warp::test::request()
+        .method("POST")
+        .path("/5/manifest")
+        .header("content-type", content_type)
+        .body(body)
+        .reply(&common::routes())
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        res.body(),
+        r#"Magic keyword not provided: none of ["Christmas 2024"] found in keywords"#
+    );
+}
+
+#[tokio::test]
+async fn submission_pages_past_the_offset_range_are_rejected() {
+    for page in [u64::MAX, u64::MAX / 20 + 2] {
+        let res = warp::test::request()
+            .path(&format!("/5/submissions?page={page}&per_page=20"))
+            .reply(&common::routes())
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "page {page}");
+    }
+}
+
+async fn diff(parts: &[(&str, &str)]) -> (StatusCode, Value) {
+    let (content_type, body) = common::multipart(parts);
+    let res = warp::test::request()
+        .method("POST")
+        .path("/5/manifest/diff")
+        .header("content-type", content_type)
+        .body(body)
+        .reply(&common::routes())
+        .await;
+    let diff = serde_json::from_slice(res.body()).unwrap_or(Value::Null);
+    (res.status(), diff)
+}
+
+#[tokio::test]
+async fn diff_reports_package_and_keyword_changes() {
+    let old = "[package]\nname = \"member\"\nversion = \"0.1.0\"\nkeywords = [\"a\", \"b\"]\n";
+    let new = "[package]\nname = \"member\"\nversion = \"0.2.0\"\nkeywords = [\"b\", \"c\"]\n";
+    let (status, diff) = diff(&[("old", old), ("new", new)]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        diff["package"],
+        json!([{ "field": "version", "old": "0.1.0", "new": "0.2.0" }])
+    );
+    assert_eq!(
+        diff["keywords"],
+        json!({ "added": ["c"], "removed": ["a"] })
+    );
+}
+
+#[tokio::test]
+async fn diff_resolves_workspace_keywords() {
+    let old = "[package]\nname = \"member\"\nkeywords = [\"Easter\"]\n";
+    let new = "[package]\nname = \"member\"\nkeywords.workspace = true\n";
+    let (status, diff) = diff(&[("old", old), ("new", new), ("workspace", WORKSPACE)]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        diff["keywords"],
+        json!({ "added": ["Christmas 2024"], "removed": ["Easter"] })
+    );
+}
+
+#[tokio::test]
+async fn diff_rejects_an_invalid_workspace() {
+    let member = "[package]\nname = \"member\"\n";
+    let (status, _) = diff(&[
+        ("old", member),
+        ("new", member),
+        ("workspace", "[workspace\n"),
+    ])
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn glob_keyword_policies_match_compiled_patterns() {
+    use shuttlings_cch24::keyword_policy::{KeywordPolicy, PolicyError};
+
+    let policy =
+        "keywords = [\"xmas-*\", \"Christmas 2024\"]\nglob = true\ncase_insensitive = true\n";
+    let policy = KeywordPolicy::from_toml(policy).unwrap();
+    let manifest = |keyword: &str| -> cargo_manifest::Manifest {
+        toml::from_str(&format!(
+            "[package]\nname = \"member\"\nkeywords = [\"{keyword}\"]\n"
+        ))
+        .unwrap()
+    };
+    assert!(policy.check(&manifest("XMAS-2024")).is_ok());
+    assert!(policy.check(&manifest("christmas 2024")).is_ok());
+    assert!(policy.check(&manifest("Easter")).is_err());
+
+    let e = KeywordPolicy::from_toml("keywords = [\"[\"]\nglob = true\n").unwrap_err();
+    assert!(matches!(e, PolicyError::Pattern { .. }), "{e}");
+}
+
+#[test]
+fn keyword_policies_without_keywords_are_rejected() {
+    use shuttlings_cch24::keyword_policy::{KeywordPolicy, PolicyError};
+
+    let e = KeywordPolicy::from_toml("keywords = []\nmode = \"all\"\n").unwrap_err();
+    assert!(matches!(e, PolicyError::NoKeywords), "{e}");
+    assert!(KeywordPolicy::from_toml("keywords = [\"Christmas 2024\"]\n").is_ok());
+}
+
+async fn raw_verbose_report(content_type: &str, manifest: &str) -> (StatusCode, Value) {
+    let res = warp::test::request()
+        .method("POST")
+        .path("/5/manifest?verbose=true")
+        .header("content-type", content_type)
+        .body(manifest)
+        .reply(&common::routes())
+        .await;
+    let report = serde_json::from_slice(res.body()).unwrap();
+    (res.status(), report)
+}
+
+#[tokio::test]
+async fn json_and_yaml_manifests_give_the_same_orders_as_toml() {
+    let toml = r#"
+[package]
+name = "member"
+keywords = ["Christmas 2024"]
+
+[package.metadata]
+orders = [{ item = "Toy car", quantity = 2 }, { item = "Lego brick", quantity = 230 }]
+"#;
+    let json = r#"{
+  "package": {
+    "name": "member",
+    "keywords": ["Christmas 2024"],
+    "metadata": {
+      "orders": [{ "item": "Toy car", "quantity": 2 }, { "item": "Lego brick", "quantity": 230 }]
+    }
+  }
+}"#;
+    let yaml = r#"
+package:
+  name: member
+  keywords: ["Christmas 2024"]
+  metadata:
+    orders:
+      - item: Toy car
+        quantity: 2
+      - item: Lego brick
+        quantity: 230
+"#;
+    let (status, expected) = raw_verbose_report("application/toml", toml).await;
+    assert_eq!(status, StatusCode::OK, "{expected}");
+    assert_eq!(
+        expected["orders"],
+        json!([
+            { "item": "Toy car", "quantity": 2 },
+            { "item": "Lego brick", "quantity": 230 },
+        ])
+    );
+    for (content_type, manifest) in [
+        ("application/json", json),
+        ("application/json; charset=utf-8", json),
+        ("application/yaml", yaml),
+        ("application/yaml; charset=utf-8", yaml),
+    ] {
+        let (status, report) = raw_verbose_report(content_type, manifest).await;
+        assert_eq!(status, StatusCode::OK, "{content_type}: {report}");
+        assert_eq!(report["orders"], expected["orders"], "{content_type}");
+    }
+}
+
+#[tokio::test]
+async fn yaml_problems_point_at_the_error() {
+    let yaml = "package:\n  name: member\n  keywords: [\"Christmas 2024\"\n";
+    let (status, report) = raw_verbose_report("application/yaml", yaml).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let start = &report["problems"][0]["span"]["start"];
+    assert_eq!(*start, json!({ "line": 4, "column": 1 }), "{report}");
+}
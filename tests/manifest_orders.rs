@@ -0,0 +1,97 @@
+//! Orders of `/5/manifest`, which stores every valid manifest and so needs a database
+
+mod common;
+
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use warp::http::StatusCode;
+
+const ORDERS: &str = r#"
+[package]
+name = "orders"
+keywords = ["Christmas 2024"]
+
+[package.metadata]
+orders = [
+    { item = "Toy car", quantity = 2 },
+    { item = "Lego brick", quantity = 230 },
+    { item = "Toy car", quantity = 3 },
+    { item = "Doll", quantity = 2 },
+]
+"#;
+
+async fn order(pool: PgPool, query: &str, manifest: &str) -> (StatusCode, String) {
+    let res = warp::test::request()
+        .method("POST")
+        .path(&format!("/5/manifest{query}"))
+        .header("content-type", "application/toml")
+        .body(manifest)
+        .reply(&common::routes_with(pool))
+        .await;
+    let body = String::from_utf8(res.body().to_vec()).unwrap();
+    (res.status(), body)
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn duplicate_items_are_merged_at_their_first_position(pool: PgPool) {
+    let (status, body) = order(pool, "?merge=true", ORDERS).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body, "Toy car: 5\nLego brick: 230\nDoll: 2");
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn orders_are_sorted_by_item(pool: PgPool) {
+    let (status, body) = order(pool.clone(), "?sort=item", ORDERS).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body, "Doll: 2\nLego brick: 230\nToy car: 2\nToy car: 3");
+
+    let (status, body) = order(pool, "?sort=item&order=desc", ORDERS).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body, "Toy car: 2\nToy car: 3\nLego brick: 230\nDoll: 2");
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn orders_are_sorted_by_quantity_keeping_ties_in_order(pool: PgPool) {
+    let (status, body) = order(pool.clone(), "?sort=quantity", ORDERS).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body, "Toy car: 2\nDoll: 2\nToy car: 3\nLego brick: 230");
+
+    let (status, body) = order(pool, "?sort=quantity&order=desc", ORDERS).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body, "Lego brick: 230\nToy car: 3\nToy car: 2\nDoll: 2");
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn json_lists_orders_with_their_total_quantity(pool: PgPool) {
+    let (status, body) = order(pool, "?merge=true&format=json", ORDERS).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        body["orders"],
+        json!([
+            { "item": "Toy car", "quantity": 5 },
+            { "item": "Lego brick", "quantity": 230 },
+            { "item": "Doll", "quantity": 2 },
+        ])
+    );
+    assert_eq!(body["total_quantity"], 237);
+}
+
+#[sqlx::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn no_orders_are_still_json(pool: PgPool) {
+    let manifest = "[package]\nname = \"orders\"\nkeywords = [\"Christmas 2024\"]\n";
+    let (status, body) = order(pool.clone(), "?format=json", manifest).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["orders"], json!([]));
+    assert_eq!(body["total_quantity"], 0);
+
+    let (status, body) = order(pool, "", manifest).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(body, "");
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use warp::http::StatusCode;
+
+async fn convert(body: &'static [u8]) -> warp::http::Response<bytes::Bytes> {
+    convert_at("/9/milk", body).await
+}
+
+async fn convert_at(path: &str, body: &'static [u8]) -> warp::http::Response<bytes::Bytes> {
+    warp::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-type", "application/json")
+        .body(body)
+        .reply(&common::routes())
+        .await
+}
+
+#[tokio::test]
+async fn invalid_conversions_report_the_rate_limit() {
+    for body in [
+        &b"{\"liters\": \"many\"}"[..],
+        b"{\"to\": \"ml\"}",
+        b"\xff\xfe",
+    ] {
+        let res = convert(body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body:?}");
+        assert_eq!(res.headers()["ratelimit-limit"], "5", "{body:?}");
+        assert_eq!(res.headers()["ratelimit-remaining"], "4", "{body:?}");
+    }
+}
+
+fn converted(res: &warp::http::Response<bytes::Bytes>) -> serde_json::Value {
+    assert_eq!(res.status(), StatusCode::OK, "{:?}", res.body());
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+#[tokio::test]
+async fn legacy_requests_convert_to_the_partner_unit() {
+    for (body, unit, expected) in [
+        (&b"{\"liters\": 1}"[..], "gallons", 0.264172),
+        (b"{\"gallons\": 1}", "liters", 3.785412),
+        (b"{\"litres\": 1}", "pints", 1.759754),
+        (b"{\"pints\": 1}", "litres", 0.568261),
+    ] {
+        let json = converted(&convert(body).await);
+        let object = json.as_object().unwrap();
+        assert_eq!(object.len(), 1, "{json}");
+        let value = object[unit].as_f64().unwrap();
+        assert!((value - expected).abs() < 1e-5, "{json}");
+    }
+}
+
+#[tokio::test]
+async fn requests_with_a_target_convert_to_it_as_named() {
+    let json = converted(&convert(b"{\"from\": {\"cups\": 2}, \"to\": \"ml\"}").await);
+    let value = json["ml"].as_f64().unwrap();
+    assert!((value - 473.176473).abs() < 1e-9, "{json}");
+
+    let json = converted(&convert(b"{\"from\": {\"l\": 1}, \"to\": \"liters\"}").await);
+    assert_eq!(json, serde_json::json!({"liters": 1.0}));
+
+    for body in [
+        &b"{\"from\": {\"cups\": 2}}"[..],
+        b"{\"from\": {\"cups\": 2}, \"to\": \"ml\", \"by\": \"me\"}",
+        b"{\"from\": {\"cups\": 2}, \"to\": \"barrels\"}",
+    ] {
+        let res = convert(body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body:?}");
+    }
+}
+
+#[tokio::test]
+async fn legacy_requests_without_a_partner_unit_are_rejected() {
+    for body in [
+        &b"{\"cups\": 2}"[..],
+        b"{\"ml\": 2}",
+        b"{\"l\": 2}",
+        b"{\"liters\": 1, \"gallons\": 1}",
+    ] {
+        let res = convert(body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body:?}");
+    }
+}
+
+#[tokio::test]
+async fn conversions_beyond_the_largest_number_are_rejected() {
+    for (path, body) in [
+        ("/9/milk", &b"{\"gallons\": 1e38}"[..]),
+        ("/9/milk", b"{\"liters\": 1e39}"),
+        (
+            "/9/milk",
+            b"{\"from\": {\"imp_gal\": 1e307}, \"to\": \"ml\"}",
+        ),
+        (
+            "/9/milk?precision=2",
+            b"{\"from\": {\"imp_gal\": 1e307}, \"to\": \"ml\"}",
+        ),
+    ] {
+        let res = convert_at(path, body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{path} {body:?}");
+    }
+}
+
+#[tokio::test]
+async fn withdrawals_below_a_millilitre_are_rejected() {
+    for body in [
+        &b"{\"withdraw\": {\"ml\": 0.4}}"[..],
+        b"{\"withdraw\": {\"liters\": 0.0009}}",
+        b"{\"withdraw\": {\"ml\": 0}}",
+        b"{\"withdraw\": {\"ml\": -1}}",
+    ] {
+        let res = convert(body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body:?}");
+    }
+    let json = converted(&convert(b"{\"withdraw\": {\"ml\": 1}}").await);
+    assert_eq!(json["withdrawn"], serde_json::json!({"ml": 1.0}));
+    assert_eq!(json["remaining"], serde_json::json!({"ml": 4999.0}));
+}
@@ -0,0 +1,43 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use lib::bucket::clients::ClientKey;
+use lib::bucket::{milk::RefillRate, ClientBuckets, Liters};
+use shuttlings_cch24 as lib;
+
+fn clients() -> lib::bucket::clients::Builder<Liters, RefillRate> {
+    ClientBuckets::builder()
+        .full(5.0)
+        .refill_rate(RefillRate::new(Liters(1.0), Duration::from_secs(1)))
+}
+
+#[tokio::test]
+async fn claims_beyond_the_limit_are_keyed_by_address() {
+    let clients = clients().claims_per_addr(2).build();
+    let addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+    for claim in ["a", "b"] {
+        let key = clients.claim_key(claim.to_string(), Some(addr)).await;
+        assert_eq!(key, ClientKey::Claim(claim.to_string()));
+    }
+    let key = clients.claim_key("c".to_string(), Some(addr)).await;
+    assert_eq!(key, ClientKey::Addr(addr));
+    // known claims stay usable, from anywhere
+    let key = clients.claim_key("a".to_string(), Some(other)).await;
+    assert_eq!(key, ClientKey::Claim("a".to_string()));
+    let key = clients.claim_key("c".to_string(), Some(other)).await;
+    assert_eq!(key, ClientKey::Claim("c".to_string()));
+}
+
+#[tokio::test]
+async fn no_buckets_beyond_max_clients() {
+    let clients = clients().max_clients(2).build();
+    let key = |n| ClientKey::Addr(IpAddr::V4(Ipv4Addr::new(10, 0, 0, n)));
+
+    assert!(clients.get(key(1)).await.is_some());
+    assert!(clients.get(key(2)).await.is_some());
+    assert!(clients.get(key(3)).await.is_none());
+    assert!(clients.get(key(1)).await.is_some());
+    assert_eq!(clients.statuses().await.len(), 2);
+}
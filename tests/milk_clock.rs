@@ -0,0 +1,260 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use warp::http::StatusCode;
+
+use lib::bucket::clock::{Clock, ManualClock};
+use lib::bucket::milk::RefillRate;
+use lib::bucket::{ClientBuckets, Liters, MilkBucket};
+use shuttlings_cch24 as lib;
+
+const SEC: Duration = Duration::from_secs(1);
+
+/// bucket refilled by 1 liter per second on `clock`, with its refill task running
+fn bucket(clock: &ManualClock, full: f32, initial: f32) -> MilkBucket {
+    let bucket = MilkBucket::builder()
+        .full(full)
+        .initial(initial)
+        .refill_rate(RefillRate::per_sec(Liters(1.0)))
+        .clock(Arc::new(clock.clone()))
+        .build();
+    tokio::spawn(bucket.clone().refill_task());
+    bucket
+}
+
+#[tokio::test]
+async fn refills_exactly_once_per_interval() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 5.0, 0.0);
+    clock.advance(SEC / 2).await;
+    assert_eq!(bucket.available().await, Liters(0.0));
+    clock.advance(SEC / 2).await;
+    assert_eq!(bucket.available().await, Liters(1.0));
+    clock.advance(SEC * 3).await;
+    assert_eq!(bucket.available().await, Liters(4.0));
+}
+
+#[tokio::test]
+async fn refill_stops_at_full() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 5.0, 3.0);
+    clock.advance(SEC * 10).await;
+    assert_eq!(bucket.available().await, Liters(5.0));
+}
+
+#[tokio::test]
+async fn fill_by_caps_at_full_and_withdrawals_take_exact_amounts() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 5.0, 4.0);
+    bucket.fill_by(Liters(3.0)).await;
+    assert_eq!(bucket.available().await, Liters(5.0));
+
+    let pack = bucket.try_withdraw(Liters(2.0)).await.unwrap();
+    assert_eq!(pack.remaining(), Liters(3.0));
+    assert_eq!(pack.next_refill(), SEC);
+
+    let empty = bucket.try_withdraw(Liters(4.0)).await.unwrap_err();
+    assert_eq!(empty.available(), Liters(3.0));
+    assert_eq!(empty.retry_after(), Some(SEC));
+    assert_eq!(bucket.available().await, Liters(3.0));
+
+    bucket.fill_by(Liters(10.0)).await;
+    assert_eq!(bucket.available().await, Liters(5.0));
+    let empty = bucket.try_withdraw(Liters(6.0)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), None);
+}
+
+#[tokio::test]
+async fn retry_after_only_for_requests_the_bucket_can_hold() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 5.0, 0.0);
+    let empty = bucket.try_withdraw(Liters(5.0)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), Some(SEC * 5));
+    let empty = bucket.try_withdraw(Liters(5.5)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), None);
+    assert_eq!(empty.quota().reset(), Some(SEC * 5));
+}
+
+#[tokio::test]
+async fn sleeps_while_full_and_wakes_on_withdrawal() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 2.0, 2.0);
+    clock.advance(SEC * 5).await;
+    assert_eq!(clock.sleepers(), 0, "no ticks while full");
+
+    bucket.try_withdraw(Liters(2.0)).await.unwrap();
+    clock.advance(Duration::ZERO).await;
+    assert_eq!(clock.sleepers(), 1, "ticking after a withdrawal");
+    let started = clock.now();
+
+    clock.advance(SEC).await;
+    assert_eq!(bucket.available().await, Liters(1.0));
+    clock.advance(SEC).await;
+    assert_eq!(bucket.available().await, Liters(2.0));
+    assert_eq!(clock.sleepers(), 0, "asleep again once full");
+    assert_eq!(clock.now() - started, SEC * 2);
+}
+
+#[tokio::test]
+async fn rate_change_restarts_the_interval() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 10.0, 0.0);
+    clock.advance(SEC * 2).await;
+    assert_eq!(bucket.available().await, Liters(2.0));
+
+    bucket.set_refill_rate(RefillRate::new(Liters(3.0), SEC * 2));
+    clock.advance(SEC).await;
+    assert_eq!(bucket.available().await, Liters(2.0));
+    clock.advance(SEC).await;
+    assert_eq!(bucket.available().await, Liters(5.0));
+}
+
+#[tokio::test]
+async fn waiters_are_served_on_refill_ticks() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 1.0, 0.0);
+    let waiter = {
+        let bucket = bucket.clone();
+        tokio::spawn(async move { bucket.withdraw_waiting(Liters(1.0), SEC * 3).await })
+    };
+    clock.advance(SEC / 2).await;
+    assert!(!waiter.is_finished());
+    clock.advance(SEC / 2).await;
+    let pack = waiter.await.unwrap().unwrap();
+    assert_eq!(pack.remaining(), Liters(0.0));
+
+    let waiter = {
+        let bucket = bucket.clone();
+        tokio::spawn(async move { bucket.withdraw_waiting(Liters(1.0), SEC / 2).await })
+    };
+    clock.advance(SEC / 2).await;
+    assert!(waiter.await.unwrap().is_err(), "timed out before the tick");
+    assert_eq!(bucket.available().await, Liters(0.0));
+}
+
+#[tokio::test]
+async fn waiters_are_served_in_arrival_order() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 3.0, 0.0);
+    let wait = |liters| {
+        let bucket = bucket.clone();
+        tokio::spawn(async move { bucket.withdraw_waiting(Liters(liters), SEC * 10).await })
+    };
+    let first = wait(2.0);
+    clock.advance(Duration::ZERO).await;
+    let second = wait(1.0);
+    clock.advance(Duration::ZERO).await;
+    assert_eq!(bucket.waiting(), 2);
+
+    clock.advance(SEC).await;
+    assert!(
+        !second.is_finished(),
+        "enough for the second, but the first came first"
+    );
+    assert!(
+        bucket.try_withdraw(Liters(1.0)).await.is_err(),
+        "no jumping the queue"
+    );
+    clock.advance(SEC).await;
+    assert_eq!(first.await.unwrap().unwrap().remaining(), Liters(0.0));
+    assert!(!second.is_finished());
+    clock.advance(SEC).await;
+    assert_eq!(second.await.unwrap().unwrap().remaining(), Liters(0.0));
+    assert_eq!(bucket.waiting(), 0);
+}
+
+#[tokio::test]
+async fn cancelled_waiters_take_nothing() {
+    let clock = ManualClock::new();
+    let bucket = bucket(&clock, 3.0, 0.0);
+    let cancelled = {
+        let bucket = bucket.clone();
+        tokio::spawn(async move { bucket.withdraw_waiting(Liters(2.0), SEC * 10).await })
+    };
+    clock.advance(Duration::ZERO).await;
+    assert_eq!(bucket.waiting(), 1);
+    cancelled.abort();
+    assert!(cancelled.await.unwrap_err().is_cancelled());
+    assert_eq!(bucket.waiting(), 0);
+
+    clock.advance(SEC * 2).await;
+    assert_eq!(bucket.available().await, Liters(2.0));
+    let pack = bucket.try_withdraw(Liters(1.0)).await.unwrap();
+    assert_eq!(pack.remaining(), Liters(1.0));
+}
+
+/// state around `bucket` whose admin token is `"admin"`
+fn state(bucket: &MilkBucket) -> lib::routes::State {
+    // never connected to, the milk endpoints do not touch the database
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .connect_lazy("postgres://postgres@localhost/unused")
+        .unwrap();
+    lib::routes::State::builder()
+        .seek_url("https://example.com")
+        .manifest_keyword("Christmas 2024")
+        .milk_limiter(Arc::new(bucket.clone()))
+        .milk_clients(
+            ClientBuckets::builder()
+                .full(10.0)
+                .refill_rate(RefillRate::per_sec(Liters(1.0)))
+                .build(),
+        )
+        .jwt_manager(
+            lib::jwt::Manager::builder()
+                .issuer("test")
+                .key("test")
+                .expires_in(chrono::TimeDelta::seconds(60))
+                .build(),
+        )
+        .cookie_manager(lib::cookie::Manager::builder().name("gift").build())
+        .jwt_decoder(lib::jwt::Decoder::builder().pem(vec![]).build())
+        .quotes_repository(
+            lib::quotes::Repository::builder()
+                .pool(pool.clone())
+                .build(),
+        )
+        .submissions_repository(
+            lib::submissions::Repository::builder()
+                .pool(pool.clone())
+                .build(),
+        )
+        .catalog_repository(lib::catalog::Repository::builder().pool(pool).build())
+        .admin_token(Some("admin".to_string()))
+        .build()
+}
+
+#[tokio::test]
+async fn put_rate_changes_the_running_refill_task() {
+    let clock = ManualClock::new();
+    let bucket = MilkBucket::builder()
+        .full(10.0)
+        .initial(0.0)
+        .refill_rate(RefillRate::per_sec(Liters(1.0)))
+        .clock(Arc::new(clock.clone()))
+        .build();
+    let state = state(&bucket);
+    tokio::spawn(state.bg_task());
+    let routes = lib::routes::make(state);
+    clock.advance(SEC * 2).await;
+    assert_eq!(bucket.available().await, Liters(2.0));
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path("/9/rate")
+        .header("authorization", "Bearer admin")
+        .header("content-type", "application/json")
+        .body(r#"{"amount":3.0,"interval_secs":2.0}"#)
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    clock.advance(SEC).await;
+    assert_eq!(
+        bucket.available().await,
+        Liters(2.0),
+        "the old rate is gone"
+    );
+    clock.advance(SEC).await;
+    assert_eq!(bucket.available().await, Liters(5.0));
+    clock.advance(SEC * 2).await;
+    assert_eq!(bucket.available().await, Liters(8.0));
+}
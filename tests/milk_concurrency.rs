@@ -0,0 +1,282 @@
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use warp::http::StatusCode;
+use warp::Filter;
+
+use lib::bucket::{clients::KeyBy, limiter::Algorithm, milk::RefillRate, ClientBuckets, Liters};
+use lib::bucket::{MilkBucket, Storage};
+use shuttlings_cch24 as lib;
+
+const FULL: f32 = 5.0;
+const REQUESTS: usize = 100;
+const ALGORITHMS: [Algorithm; 5] = [
+    Algorithm::Bucket,
+    Algorithm::Lazy,
+    Algorithm::Gcra,
+    Algorithm::SlidingLog,
+    Algorithm::FixedWindow,
+];
+
+/// routes with a full limiter which is practically never refilled
+fn routes(
+    algorithm: Algorithm,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // never connected to, the milk endpoints do not touch the database
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .connect_lazy("postgres://postgres@localhost/unused")
+        .unwrap();
+    let rate = RefillRate::new(Liters(1.0), Duration::from_secs(3600));
+    let state = lib::routes::State::builder()
+        .seek_url("https://example.com")
+        .manifest_keyword("Christmas 2024")
+        .milk_limiter(algorithm.build(Liters(FULL), Liters(FULL), rate))
+        .milk_clients(
+            ClientBuckets::builder()
+                .full(FULL)
+                .refill_rate(rate)
+                .algorithm(algorithm)
+                .key_by(KeyBy::Global)
+                .build(),
+        )
+        .jwt_manager(
+            lib::jwt::Manager::builder()
+                .issuer("test")
+                .key("test")
+                .expires_in(chrono::TimeDelta::seconds(60))
+                .build(),
+        )
+        .cookie_manager(lib::cookie::Manager::builder().name("gift").build())
+        .jwt_decoder(lib::jwt::Decoder::builder().pem(vec![]).build())
+        .quotes_repository(
+            lib::quotes::Repository::builder()
+                .pool(pool.clone())
+                .build(),
+        )
+        .submissions_repository(
+            lib::submissions::Repository::builder()
+                .pool(pool.clone())
+                .build(),
+        )
+        .catalog_repository(lib::catalog::Repository::builder().pool(pool).build())
+        .admin_token(None)
+        .build();
+    lib::routes::make(state)
+}
+
+fn count(statuses: &[StatusCode], status: StatusCode) -> usize {
+    statuses.iter().filter(|s| **s == status).count()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_withdrawals_never_overdraw() {
+    for algorithm in ALGORITHMS {
+        concurrent_withdrawals(algorithm).await;
+    }
+}
+
+async fn concurrent_withdrawals(algorithm: Algorithm) {
+    let route = routes(algorithm);
+    let requests = (0..REQUESTS).map(|_| {
+        let route = route.clone();
+        tokio::spawn(async move {
+            warp::test::request()
+                .method("POST")
+                .path("/9/milk")
+                .reply(&route)
+                .await
+                .status()
+        })
+    });
+    let statuses: Vec<_> = join_all(requests)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        count(&statuses, StatusCode::OK),
+        FULL as usize,
+        "{algorithm:?}"
+    );
+    assert_eq!(
+        count(&statuses, StatusCode::TOO_MANY_REQUESTS),
+        REQUESTS - FULL as usize,
+        "{algorithm:?}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_conversions_share_the_bucket() {
+    for algorithm in ALGORITHMS {
+        concurrent_conversions(algorithm).await;
+    }
+}
+
+async fn concurrent_conversions(algorithm: Algorithm) {
+    let route = routes(algorithm);
+    let requests = (0..REQUESTS).map(|i| {
+        let route = route.clone();
+        tokio::spawn(async move {
+            let request = warp::test::request().method("POST").path("/9/milk");
+            let request = if i % 2 == 0 {
+                request
+                    .header("content-type", "application/json")
+                    .body(r#"{"liters":1.0}"#)
+            } else {
+                request
+            };
+            request.reply(&route).await.status()
+        })
+    });
+    let statuses: Vec<_> = join_all(requests)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        count(&statuses, StatusCode::OK),
+        FULL as usize,
+        "{algorithm:?}"
+    );
+    assert_eq!(
+        count(&statuses, StatusCode::TOO_MANY_REQUESTS),
+        REQUESTS - FULL as usize,
+        "{algorithm:?}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn both_storages_withdraw_exactly_the_level() {
+    for storage in [Storage::Mutex, Storage::Atomic] {
+        let bucket = MilkBucket::builder()
+            .full(FULL)
+            .initial(FULL)
+            .storage(storage)
+            .build();
+        let withdrawals = (0..REQUESTS).map(|_| {
+            let bucket = bucket.clone();
+            tokio::spawn(async move { bucket.try_withdraw(Liters(0.5)).await.is_ok() })
+        });
+        let withdrawn = join_all(withdrawals)
+            .await
+            .into_iter()
+            .filter(|r| *r.as_ref().unwrap())
+            .count();
+        assert_eq!(withdrawn, (FULL * 2.0) as usize, "{storage:?}");
+        assert_eq!(bucket.available().await, Liters(0.0), "{storage:?}");
+    }
+}
+
+#[tokio::test]
+async fn withdrawals_below_a_millilitre_take_one() {
+    for storage in [Storage::Mutex, Storage::Atomic] {
+        let bucket = MilkBucket::builder()
+            .full(FULL)
+            .initial(FULL)
+            .storage(storage)
+            .build();
+        let mut withdrawn = 0;
+        while let Ok(pack) = bucket.try_withdraw(Liters(0.0004)).await {
+            assert_eq!(pack.inner(), Liters(0.001), "{storage:?}");
+            withdrawn += 1;
+            assert!(withdrawn <= 5000, "{storage:?} gave more than it holds");
+        }
+        assert_eq!(withdrawn, 5000, "{storage:?}");
+        assert!(bucket.available().await.0 < 0.001, "{storage:?}");
+    }
+}
+
+#[tokio::test]
+async fn atomic_levels_are_kept_in_whole_millilitres() {
+    let level = |storage| async move {
+        let bucket = MilkBucket::builder()
+            .full(FULL)
+            .initial(1.0004)
+            .storage(storage)
+            .build();
+        bucket.available().await
+    };
+    assert_eq!(level(Storage::Mutex).await, Liters(1.0004));
+    assert_eq!(level(Storage::Atomic).await, Liters(1.0));
+
+    for storage in [Storage::Mutex, Storage::Atomic] {
+        let bucket = MilkBucket::builder()
+            .full(FULL)
+            .initial(FULL)
+            .storage(storage)
+            .build();
+        let pack = bucket.try_withdraw(Liters(1.2344)).await.unwrap();
+        assert_eq!(pack.inner(), Liters(1.234), "{storage:?}");
+    }
+}
+
+#[tokio::test]
+async fn resuming_past_unwatched_changes_starts_from_a_snapshot() {
+    use futures_util::StreamExt;
+    use lib::bucket::milk::EventKind;
+
+    for storage in [Storage::Mutex, Storage::Atomic] {
+        let bucket = MilkBucket::builder()
+            .full(FULL)
+            .initial(FULL)
+            .storage(storage)
+            .build();
+        let mut events = Box::pin(bucket.clone().level_events(None));
+        events.next().await.unwrap();
+        bucket.try_withdraw(Liters(1.0)).await.unwrap();
+        let withdrawn = events.next().await.unwrap();
+        assert_eq!(withdrawn.kind(), EventKind::Withdraw, "{storage:?}");
+        drop(events);
+
+        bucket.try_withdraw(Liters(1.0)).await.unwrap();
+        assert!(!bucket.is_watched());
+        let mut events = Box::pin(bucket.clone().level_events(Some(withdrawn.id())));
+        let resumed = events.next().await.unwrap();
+        assert_eq!(resumed.kind(), EventKind::Level, "{storage:?}");
+        assert_eq!(resumed.level(), Liters(FULL - 2.0), "{storage:?}");
+
+        bucket.try_withdraw(Liters(1.0)).await.unwrap();
+        let next = events.next().await.unwrap();
+        assert_eq!(next.kind(), EventKind::Withdraw, "{storage:?}");
+        assert_eq!(next.level(), Liters(FULL - 3.0), "{storage:?}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn events_carry_the_level_of_their_own_change() {
+    use futures_util::StreamExt;
+    use lib::bucket::milk::EventKind;
+
+    for storage in [Storage::Mutex, Storage::Atomic] {
+        let bucket = MilkBucket::builder()
+            .full(FULL)
+            .initial(FULL)
+            .storage(storage)
+            .build();
+        let mut events = Box::pin(bucket.clone().level_events(None));
+        let snapshot = events.next().await.unwrap();
+        assert_eq!(snapshot.kind(), EventKind::Level, "{storage:?}");
+        let withdrawals = (0..REQUESTS).map(|_| {
+            let bucket = bucket.clone();
+            tokio::spawn(async move { bucket.try_withdraw(Liters(0.5)).await.is_ok() })
+        });
+        join_all(withdrawals).await;
+
+        let mut received = vec![];
+        while let Ok(Some(event)) =
+            tokio::time::timeout(Duration::from_millis(100), events.next()).await
+        {
+            received.push(event);
+        }
+        let mut withdrawn: Vec<_> = received
+            .iter()
+            .filter(|e| e.kind() == EventKind::Withdraw)
+            .map(|e| e.level().0)
+            .collect();
+        withdrawn.sort_by(f32::total_cmp);
+        let expected: Vec<_> = (0..10).map(|i| i as f32 * 0.5).collect();
+        assert_eq!(withdrawn, expected, "{storage:?}");
+        let last = received.last().unwrap();
+        assert_eq!(last.level(), bucket.available().await, "{storage:?}");
+    }
+}
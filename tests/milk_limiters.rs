@@ -0,0 +1,142 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use lib::bucket::limiter::{Algorithm, RateLimiter};
+use lib::bucket::milk::RefillRate;
+use lib::bucket::Liters;
+use shuttlings_cch24 as lib;
+
+const SEC: Duration = Duration::from_secs(1);
+/// the algorithms refilled on access, without a background task
+const ON_ACCESS: [Algorithm; 4] = [
+    Algorithm::Lazy,
+    Algorithm::Gcra,
+    Algorithm::SlidingLog,
+    Algorithm::FixedWindow,
+];
+
+/// refilled by 1 liter per second
+fn limiter(algorithm: Algorithm, full: f32, initial: f32) -> Arc<dyn RateLimiter> {
+    algorithm.build(
+        Liters(full),
+        Liters(initial),
+        RefillRate::per_sec(Liters(1.0)),
+    )
+}
+
+/// let spawned tasks catch up with the paused clock
+async fn settle() {
+    for _ in 0..8 {
+        tokio::task::yield_now().await;
+    }
+}
+
+async fn advance(duration: Duration) {
+    tokio::time::advance(duration).await;
+    settle().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn lazy_refills_by_the_elapsed_time() {
+    let limiter = limiter(Algorithm::Lazy, 5.0, 0.0);
+    let empty = limiter.try_acquire(Liters(1.0)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), Some(SEC));
+    advance(SEC * 3 / 2).await;
+    let pack = limiter.try_acquire(Liters(1.0)).await.unwrap();
+    assert_eq!(pack.remaining(), Liters(0.5));
+    advance(SEC * 10).await;
+    assert_eq!(limiter.quota().await.remaining(), Liters(5.0));
+}
+
+#[tokio::test(start_paused = true)]
+async fn gcra_tolerates_bursts_of_full() {
+    let limiter = limiter(Algorithm::Gcra, 3.0, 3.0);
+    for _ in 0..3 {
+        limiter.try_acquire(Liters(1.0)).await.unwrap();
+    }
+    let empty = limiter.try_acquire(Liters(1.0)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), Some(SEC));
+    advance(SEC).await;
+    limiter.try_acquire(Liters(1.0)).await.unwrap();
+    assert!(limiter.try_acquire(Liters(1.0)).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn fixed_window_resets_when_the_window_rolls() {
+    // a window of 2 seconds
+    let limiter = limiter(Algorithm::FixedWindow, 2.0, 2.0);
+    limiter.try_acquire(Liters(2.0)).await.unwrap();
+    let empty = limiter.try_acquire(Liters(1.0)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), Some(SEC * 2));
+    advance(SEC).await;
+    let empty = limiter.try_acquire(Liters(1.0)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), Some(SEC));
+    advance(SEC).await;
+    let pack = limiter.try_acquire(Liters(2.0)).await.unwrap();
+    assert_eq!(pack.remaining(), Liters(0.0));
+}
+
+#[tokio::test(start_paused = true)]
+async fn sliding_log_frees_withdrawals_one_by_one() {
+    // a window of 2 seconds
+    let limiter = limiter(Algorithm::SlidingLog, 2.0, 2.0);
+    limiter.try_acquire(Liters(1.0)).await.unwrap();
+    advance(SEC).await;
+    limiter.try_acquire(Liters(1.0)).await.unwrap();
+    let empty = limiter.try_acquire(Liters(1.0)).await.unwrap_err();
+    assert_eq!(empty.retry_after(), Some(SEC));
+    advance(SEC).await;
+    limiter.try_acquire(Liters(1.0)).await.unwrap();
+    assert!(limiter.try_acquire(Liters(1.0)).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn waiters_are_served_in_arrival_order() {
+    for algorithm in ON_ACCESS {
+        let limiter = limiter(algorithm, 2.0, 0.0);
+        let wait = |liters| {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move { limiter.acquire_waiting(Liters(liters), SEC * 10).await })
+        };
+        let first = wait(2.0);
+        settle().await;
+        let second = wait(1.0);
+        settle().await;
+
+        advance(SEC).await;
+        assert!(!first.is_finished(), "{algorithm:?}");
+        assert!(!second.is_finished(), "{algorithm:?}: the first came first");
+        let jumped = limiter.try_acquire(Liters(1.0)).await;
+        assert!(jumped.is_err(), "{algorithm:?}: no jumping the queue");
+        while !first.is_finished() {
+            assert!(!second.is_finished(), "{algorithm:?}");
+            advance(SEC / 4).await;
+        }
+        first.await.unwrap().unwrap();
+        for _ in 0..40 {
+            if second.is_finished() {
+                break;
+            }
+            advance(SEC / 4).await;
+        }
+        second.await.unwrap().unwrap();
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancelled_waiters_take_nothing() {
+    for algorithm in ON_ACCESS {
+        let limiter = limiter(algorithm, 2.0, 0.0);
+        let cancelled = {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move { limiter.acquire_waiting(Liters(2.0), SEC * 10).await })
+        };
+        settle().await;
+        cancelled.abort();
+        assert!(cancelled.await.unwrap_err().is_cancelled());
+
+        advance(SEC * 2).await;
+        let pack = limiter.try_acquire(Liters(2.0)).await;
+        assert!(pack.is_ok(), "{algorithm:?}: {pack:?}");
+    }
+}
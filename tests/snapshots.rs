@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, TimeZone, Utc};
+use lib::bucket::clients::ClientKey;
+use lib::bucket::{milk::RefillRate, ClientBuckets, Liters};
+use lib::snapshots::model::{BucketName, MilkSnapshot, SavedLevel};
+use shuttlings_cch24 as lib;
+
+const FULL: Liters = Liters(5.0);
+
+fn rate() -> RefillRate {
+    RefillRate::new(Liters(1.0), Duration::from_secs(1))
+}
+
+fn snapshot(level: f64) -> MilkSnapshot {
+    MilkSnapshot {
+        name: BucketName::global(),
+        level: SavedLevel(level),
+        saved_at: Utc.with_ymd_and_hms(2024, 12, 9, 12, 0, 0).unwrap(),
+    }
+}
+
+#[test]
+fn restored_levels_are_credited_with_whole_ticks_of_downtime() {
+    let snapshot = snapshot(1.0);
+    let restore = |downtime| snapshot.restore(FULL, rate(), snapshot.saved_at + downtime);
+
+    assert_eq!(restore(TimeDelta::zero()), Liters(1.0));
+    assert_eq!(restore(TimeDelta::milliseconds(999)), Liters(1.0));
+    assert_eq!(restore(TimeDelta::seconds(2)), Liters(3.0));
+    assert_eq!(restore(TimeDelta::milliseconds(2500)), Liters(3.0));
+}
+
+#[test]
+fn restored_levels_are_capped_at_full() {
+    let snapshot = snapshot(1.0);
+    let restore = |downtime| snapshot.restore(FULL, rate(), snapshot.saved_at + downtime);
+
+    assert_eq!(restore(TimeDelta::seconds(4)), FULL);
+    assert_eq!(restore(TimeDelta::seconds(60)), FULL);
+    assert_eq!(restore(TimeDelta::days(365)), FULL);
+    // saved under a larger capacity
+    let snapshot = self::snapshot(8.0);
+    assert_eq!(snapshot.restore(FULL, rate(), snapshot.saved_at), FULL);
+}
+
+#[test]
+fn snapshots_from_the_future_earn_no_refill() {
+    let snapshot = snapshot(1.0);
+    let restore = |skew| snapshot.restore(FULL, rate(), snapshot.saved_at - skew);
+
+    assert_eq!(restore(TimeDelta::seconds(1)), Liters(1.0));
+    assert_eq!(restore(TimeDelta::days(1)), Liters(1.0));
+}
+
+#[test]
+fn client_bucket_names_round_trip() {
+    let keys = [
+        ClientKey::Global,
+        ClientKey::Addr("127.0.0.1".parse().unwrap()),
+        ClientKey::Addr("::1".parse().unwrap()),
+        ClientKey::Claim("client:a:b".to_string()),
+    ];
+    for key in keys {
+        let name = BucketName::client(&key);
+        assert_eq!(name.client_key(), Some(key));
+    }
+    assert_eq!(BucketName::global().client_key(), None);
+    assert_eq!(BucketName("client:nope".to_string()).client_key(), None);
+}
+
+#[tokio::test]
+async fn restored_client_buckets_keep_their_level() {
+    let clients = ClientBuckets::builder()
+        .full(FULL.0)
+        .refill_rate(rate())
+        .build();
+    let key = ClientKey::Claim("a".to_string());
+    clients.restore(key.clone(), Liters(2.0)).await;
+    // an existing bucket is left as is
+    clients.restore(key.clone(), Liters(4.0)).await;
+
+    let statuses = clients.statuses().await;
+    assert_eq!(statuses.len(), 1);
+    assert_eq!(statuses[0].client(), &key);
+    assert_eq!(statuses[0].level(), Liters(2.0));
+}
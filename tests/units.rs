@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use lib::bucket::unit::{self, Quantity, VolumeUnit, UNITS};
+use shuttlings_cch24 as lib;
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() <= expected.abs() * 1e-12,
+        "{actual} != {expected}"
+    );
+}
+
+#[test]
+fn units_are_defined_by_their_liters() {
+    for (unit, liters) in [
+        (&unit::MILLILITERS, 0.001),
+        (&unit::LITERS, 1.0),
+        (&unit::LITRES, 1.0),
+        (&unit::TEASPOONS, 0.00492892159375),
+        (&unit::TABLESPOONS, 0.01478676478125),
+        (&unit::FLUID_OUNCES, 0.0295735295625),
+        (&unit::CUPS, 0.2365882365),
+        (&unit::US_PINTS, 0.473176473),
+        (&unit::QUARTS, 0.946352946),
+        (&unit::GALLONS, 3.785411784),
+        (&unit::IMPERIAL_FLUID_OUNCES, 0.0284130625),
+        (&unit::PINTS, 0.56826125),
+        (&unit::IMPERIAL_QUARTS, 1.1365225),
+        (&unit::IMPERIAL_GALLONS, 4.54609),
+    ] {
+        assert_close(unit.to_liters(1.0), liters);
+        assert_close(unit.from_liters(liters), 1.0);
+    }
+}
+
+#[test]
+fn units_relate_as_customary() {
+    for (from, value, to, expected) in [
+        (&unit::TABLESPOONS, 1.0, &unit::TEASPOONS, 3.0),
+        (&unit::FLUID_OUNCES, 1.0, &unit::TABLESPOONS, 2.0),
+        (&unit::CUPS, 1.0, &unit::FLUID_OUNCES, 8.0),
+        (&unit::US_PINTS, 1.0, &unit::CUPS, 2.0),
+        (&unit::QUARTS, 1.0, &unit::US_PINTS, 2.0),
+        (&unit::GALLONS, 1.0, &unit::QUARTS, 4.0),
+        (&unit::PINTS, 1.0, &unit::IMPERIAL_FLUID_OUNCES, 20.0),
+        (&unit::IMPERIAL_QUARTS, 1.0, &unit::PINTS, 2.0),
+        (&unit::IMPERIAL_GALLONS, 1.0, &unit::IMPERIAL_QUARTS, 4.0),
+        (&unit::LITERS, 1.0, &unit::MILLILITERS, 1000.0),
+    ] {
+        assert_close(from.convert(value, to), expected);
+    }
+}
+
+#[test]
+fn units_are_found_by_name_and_alias() {
+    for unit in UNITS {
+        assert_eq!(VolumeUnit::find(unit.name()), Some(unit));
+        for alias in unit.aliases() {
+            assert_eq!(VolumeUnit::find(alias), Some(unit), "{alias}");
+        }
+    }
+    assert_eq!(VolumeUnit::find("ml"), Some(&unit::MILLILITERS));
+    assert_eq!(VolumeUnit::find("fl_oz"), Some(&unit::FLUID_OUNCES));
+    assert_eq!(VolumeUnit::find("imp_gal"), Some(&unit::IMPERIAL_GALLONS));
+    assert_eq!(VolumeUnit::find("ML"), None);
+    assert_eq!(VolumeUnit::find("barrels"), None);
+}
+
+#[test]
+fn unit_names_and_aliases_are_unique() {
+    let mut names = HashSet::new();
+    for unit in UNITS {
+        for name in std::iter::once(&unit.name()).chain(unit.aliases()) {
+            assert!(names.insert(*name), "{name} is taken twice");
+        }
+    }
+}
+
+#[test]
+fn quantities_keep_the_requested_name() {
+    let quantity: Quantity = serde_json::from_str(r#"{"ml": 250}"#).unwrap();
+    assert_eq!(quantity.name(), "ml");
+    assert_eq!(quantity.unit(), &unit::MILLILITERS);
+    assert_eq!(quantity.value(), 250.0);
+
+    let converted = quantity.convert_to("cup", None).unwrap();
+    assert_eq!(converted.unit(), &unit::CUPS);
+    let json = serde_json::to_value(&converted).unwrap();
+    assert_close(json["cup"].as_f64().unwrap(), 250.0 / 236.5882365);
+}
+
+#[test]
+fn quantities_need_exactly_one_known_unit() {
+    for json in [r#"{}"#, r#"{"ml": 1, "cups": 2}"#, r#"{"barrels": 1}"#] {
+        assert!(serde_json::from_str::<Quantity>(json).is_err(), "{json}");
+    }
+    let quantity = Quantity::new(&unit::LITERS, 1.0);
+    assert!(quantity.convert_to("barrels", None).is_err());
+}
+
+#[test]
+fn conversions_too_large_for_the_unit_fail() {
+    let quantity = Quantity::new(&unit::IMPERIAL_GALLONS, 1e307);
+    assert!(quantity.convert_to("ml", None).is_err());
+    assert!(quantity.convert_to("imp_gal", None).is_ok());
+}